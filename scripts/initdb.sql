@@ -1,7 +1,64 @@
 create table counts (
-	username VARCHAR(32) PRIMARY KEY,
-	chatId INT NOT NULL,
+	userId BIGINT PRIMARY KEY,
+	username VARCHAR(32),
+	chatId BIGINT NOT NULL,
 	payLimit DOUBLE DEFAULT 180.00,
+	baseLimit DOUBLE,
+	warnPercent DOUBLE DEFAULT 80.00,
+	language VARCHAR(8),
+	notifySummary BOOLEAN DEFAULT TRUE,
+	notifyAnnouncements BOOLEAN DEFAULT TRUE,
+	notifyWarnings BOOLEAN DEFAULT TRUE,
+	notifyReminders BOOLEAN DEFAULT TRUE,
+	lastExpense TIMESTAMP NULL,
+	quickAdd VARCHAR(128),
 	autoReset BOOLEAN DEFAULT TRUE,
-	paid DOUBLE
-);
\ No newline at end of file
+	active BOOLEAN DEFAULT TRUE,
+	paid DOUBLE,
+	liters DOUBLE DEFAULT 0,
+	paidWithLiters DOUBLE DEFAULT 0
+) default charset = utf8mb4;
+
+create table journal (
+	id INT AUTO_INCREMENT PRIMARY KEY,
+	userId BIGINT NOT NULL,
+	paid DOUBLE,
+	liters DOUBLE,
+	paidWithLiters DOUBLE DEFAULT 0,
+	INDEX (userId)
+) default charset = utf8mb4;
+
+create table audit (
+	id INT AUTO_INCREMENT PRIMARY KEY,
+	userId BIGINT NOT NULL,
+	field VARCHAR(32) NOT NULL,
+	oldValue VARCHAR(128),
+	newValue VARCHAR(128),
+	changedAt TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+	source VARCHAR(16) NOT NULL DEFAULT 'user',
+	INDEX (userId)
+) default charset = utf8mb4;
+
+create table processed (
+	chatId BIGINT NOT NULL,
+	messageId INT NOT NULL,
+	processedAt TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+	PRIMARY KEY (chatId, messageId)
+) default charset = utf8mb4;
+
+create table recurring (
+	id INT AUTO_INCREMENT PRIMARY KEY,
+	userId BIGINT NOT NULL,
+	amount DOUBLE NOT NULL,
+	cadence VARCHAR(8) NOT NULL,
+	day INT NOT NULL,
+	INDEX (userId)
+) default charset = utf8mb4;
+
+create table state (
+	name VARCHAR(32) PRIMARY KEY,
+	value VARCHAR(64)
+) default charset = utf8mb4;
+
+-- Number of the last migration in scripts/migrations, every migration updates it
+insert into state(name, value) values ('schemaVersion', '18');
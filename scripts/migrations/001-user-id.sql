@@ -0,0 +1,9 @@
+-- Key users by Telegram user ID instead of username
+alter table counts modify chatId BIGINT NOT NULL;
+alter table counts add column userId BIGINT first;
+-- Private chats share their ID with the user
+update counts set userId = chatId where chatId > 0;
+-- Group chats do not, those users get a temporary negative ID until /start claims the row by username
+set @pending = 0;
+update counts set userId = (@pending := @pending - 1) where chatId < 0 order by username;
+alter table counts drop primary key, modify username VARCHAR(32), add primary key (userId);
@@ -0,0 +1,2 @@
+-- Track the liters filled along with the amount paid
+alter table counts add column liters DOUBLE DEFAULT 0;
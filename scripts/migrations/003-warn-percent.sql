@@ -0,0 +1,2 @@
+-- Percentage of the limit at which the user is warned
+alter table counts add column warnPercent DOUBLE DEFAULT 80.00;
@@ -0,0 +1,2 @@
+-- Language of the messages, NULL to use the one of the Telegram client
+alter table counts add column language VARCHAR(8);
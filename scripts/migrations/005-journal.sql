@@ -0,0 +1,8 @@
+-- Previous amounts of each user to be able to undo changes
+create table journal (
+	id INT AUTO_INCREMENT PRIMARY KEY,
+	userId BIGINT NOT NULL,
+	paid DOUBLE,
+	liters DOUBLE,
+	INDEX (userId)
+);
@@ -0,0 +1,10 @@
+-- Every change of the user data, shown in /history
+create table audit (
+	id INT AUTO_INCREMENT PRIMARY KEY,
+	userId BIGINT NOT NULL,
+	field VARCHAR(16) NOT NULL,
+	oldValue VARCHAR(32),
+	newValue VARCHAR(32),
+	changedAt TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+	INDEX (userId)
+);
@@ -0,0 +1,5 @@
+-- Notifications each user can switch off, with the audit field widened to fit their names
+alter table counts add column notifySummary BOOLEAN DEFAULT TRUE;
+alter table counts add column notifyAnnouncements BOOLEAN DEFAULT TRUE;
+alter table counts add column notifyWarnings BOOLEAN DEFAULT TRUE;
+alter table audit modify field VARCHAR(32) NOT NULL;
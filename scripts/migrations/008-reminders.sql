@@ -0,0 +1,3 @@
+-- Remind users that have not recorded an expense for a while
+alter table counts add column notifyReminders BOOLEAN DEFAULT TRUE;
+alter table counts add column lastExpense TIMESTAMP NULL;
@@ -0,0 +1,7 @@
+-- Messages already handled, to skip the ones Telegram delivers again
+create table processed (
+	chatId BIGINT NOT NULL,
+	messageId INT NOT NULL,
+	processedAt TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+	PRIMARY KEY (chatId, messageId)
+);
@@ -0,0 +1,2 @@
+-- Usual amounts shown as buttons
+alter table counts add column quickAdd VARCHAR(64);
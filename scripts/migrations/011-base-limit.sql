@@ -0,0 +1,2 @@
+-- Limit to restore next month when the current one is for this month only
+alter table counts add column baseLimit DOUBLE;
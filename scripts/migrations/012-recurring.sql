@@ -0,0 +1,9 @@
+-- Expenses recorded automatically every month or week
+create table recurring (
+	id INT AUTO_INCREMENT PRIMARY KEY,
+	userId BIGINT NOT NULL,
+	amount DOUBLE NOT NULL,
+	cadence VARCHAR(8) NOT NULL,
+	day INT NOT NULL,
+	INDEX (userId)
+);
@@ -0,0 +1,2 @@
+-- Users who blocked the bot are skipped in notifications until they write again
+alter table counts add column active BOOLEAN DEFAULT TRUE;
@@ -0,0 +1,4 @@
+-- Fit 8 quick add amounts, also when audited
+alter table counts modify quickAdd VARCHAR(128);
+alter table audit modify oldValue VARCHAR(128);
+alter table audit modify newValue VARCHAR(128);
@@ -0,0 +1,6 @@
+-- Amount spent on expenses with liters, so expenses without them do not raise the average price
+alter table counts add column paidWithLiters DOUBLE DEFAULT 0;
+alter table journal add column paidWithLiters DOUBLE DEFAULT 0;
+-- Best guess for existing amounts, which cannot be told apart
+update counts set paidWithLiters = paid where liters > 0;
+update journal set paidWithLiters = paid where liters > 0;
@@ -0,0 +1,5 @@
+-- Values kept between restarts, like the last month started
+create table state (
+	name VARCHAR(32) PRIMARY KEY,
+	value VARCHAR(64)
+);
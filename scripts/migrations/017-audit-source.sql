@@ -0,0 +1,2 @@
+-- Who made each change, the user or the scheduler for automatic ones like the monthly reset
+alter table audit add column source VARCHAR(16) NOT NULL DEFAULT 'user';
@@ -0,0 +1,8 @@
+-- Tables in utf8mb4 as checked on startup, and the number of the last migration applied, every migration from now on updates it
+alter table counts convert to character set utf8mb4;
+alter table journal convert to character set utf8mb4;
+alter table audit convert to character set utf8mb4;
+alter table processed convert to character set utf8mb4;
+alter table recurring convert to character set utf8mb4;
+alter table state convert to character set utf8mb4;
+insert into state(name, value) values ('schemaVersion', '18') on duplicate key update value = '18';
@@ -1,73 +1,962 @@
 const TeleBot = require('telebot');
+const zlib = require('zlib');
+const fs = require('fs');
 const Db = require('./db.js');
-const config = require('./config.js');
+const Scheduler = require('./scheduler.js');
+const Messages = require('./messages.js');
+const Health = require('./health.js');
+const Webhook = require('./webhook.js');
+const Prices = require('./prices.js');
+const Text = require('./text.js');
+const config = require('./settings.js');
+
+//With --check the configuration, database and token are validated and the bot exits without starting
+const checkOnly = process.argv.includes('--check');
+
+if (process.env.TELEGRAM_TOKEN_FILE) {
+    config.api.token = fs.readFileSync(process.env.TELEGRAM_TOKEN_FILE, 'utf8').trim();
+}
 
 const bot = new TeleBot(config.api);
 
 const data = new Db.Db();
 
+const scheduler = new Scheduler.Scheduler();
+
+const prices = new Prices.Prices();
+
+//Texts handled as expenses, also used to tell which texts no handler understands
+//Amounts may have thousands separators or a decimal comma, see Text.parseAmount
+const numberPattern = /^\d[\d.,]*$/;
+const litersPattern = /^(\d[\d.,]*) (\d[\d.,]*)L$/i;
+const negativePattern = /^-\d+\.*\d*/;
+
+//Time before the same chat gets another hint for a text the bot does not understand
+const hintCooldown = 10 * 60 * 1000;
+const hinted = new Map();
+
+//Longest text Telegram accepts in a single message
+const maxMessageLength = 4096;
+
+//Handler errors the user was told about with a fallback reply
+let handlerErrors = 0;
+
+//HEALTH_PORT lets docker-compose serve the endpoint its healthcheck polls
+const healthPort = process.env.HEALTH_PORT || config.app.healthPort;
+
+const health = !checkOnly && healthPort ? new Health.Health(healthPort, {
+    db: () => data.ping(),
+    telegram: () => bot.getMe()
+}, () => Object.assign({handlerErrors}, data.metrics)) : null;
+
+//Errors a handler does not catch itself get the fallback reply too, callbacks in the chat of their button
+const on = bot.on.bind(bot);
+bot.on = (types, handler) => on(types, (msg, ...args) => {
+    const origin = msg.message ? {from: msg.from, chat: msg.message.chat} : msg;
+    try {
+        return Promise.resolve(handler(msg, ...args)).catch(failed(origin, "handling " + types));
+    } catch (err) {
+        failed(origin, "handling " + types)(err);
+    }
+});
+
+//Messages from users not allowed are answered and dropped before reaching any handler
+bot.mod('message', (update) => {
+    const msg = update.message;
+    if (msg.from && !isAllowed(msg.from)) {
+        console.log("Refusing message from " + msg.from.id);
+        send(msg.chat.id, Messages.text(msg.from.language_code, 'notAllowed'))
+            .catch(err => console.log("Error refusing message", err));
+        update.message = {};
+    }
+    return update;
+});
+
+//Notifications go to the private chat the user last wrote from, in case the old one is gone
+bot.on('*', (msg) => {
+    if (!msg.from || !msg.chat) {
+        return;
+    }
+    data.reactivate(msg.from.id)
+        .then(reactivated => reactivated && console.log("Reactivated " + msg.from.id))
+        .catch(err => console.log("Error reactivating " + msg.from.id, err));
+    data.setDefaultLanguage(msg.from.id, Messages.supported(msg.from.language_code))
+        .catch(err => console.log("Error storing language of " + msg.from.id, err));
+    if (msg.chat.type != 'private') {
+        return;
+    }
+    data.updateChatId(msg.from.id, msg.chat.id)
+        .then(updated => updated && console.log("Updated chat of " + msg.from.id + " to " + msg.chat.id))
+        .catch(err => console.log("Error updating chat of " + msg.from.id, err));
+});
+
+//Registered users just get their data, with the quick add keyboard again in case the client lost it
 bot.on('/start', (msg) => {
-    data.start(msg.from.username, msg.chat.id)
-    .then(() => sendData(msg))
-    .catch(err => console.log("Error starting", err));
+    data.start(msg.from.id, msg.from.username, msg.chat.id, Messages.supported(msg.from.language_code))
+    .then(() => data.getQuickAdd(msg.from.id))
+    .then(quickAdd => sendData(msg.from.id, msg.chat.id, null,
+        quickAdd && (() => bot.keyboard([quickAdd.split(' ')], {resize: true}))))
+    .catch(failed(msg, "starting"));
 });
 
 bot.on('/reset', (msg) => {
-    data.reset(msg.from.username)
-    .then(() => sendData(msg))
-    .catch(err => console.log("Error resetting", err));
+    Promise.all([data.getAmount(msg.from.id), languageOf(msg.from)])
+        .then(([num, lang]) => {
+            const replyMarkup = bot.inlineKeyboard([[
+                bot.inlineButton(Messages.text(lang, 'resetConfirm', round(num, 2)), {callback: 'reset:' + msg.from.id}),
+                bot.inlineButton(Messages.text(lang, 'cancel'), {callback: 'cancel:' + msg.from.id})
+            ]]);
+            return send(msg.chat.id, Messages.text(lang, 'resetQuestion'), {replyMarkup});
+        })
+        .catch(failed(msg, "asking reset confirmation"));
+});
+
+bot.on('/undo', (msg) => {
+    Promise.all([data.undo(msg.from.id), languageOf(msg.from)])
+        .then(([undone, lang]) => send(msg.chat.id, Messages.text(lang, undone ? 'undoDone' : 'nothingToUndo')))
+        .then(() => sendData(msg.from.id, msg.chat.id))
+        .catch(failed(msg, "undoing"));
+});
+
+bot.on(/^\/announce ([\s\S]+)$/, (msg, props) => {
+    languageOf(msg.from)
+        .then(lang => {
+            if (!isAdmin(msg.from)) {
+                return send(msg.chat.id, Messages.text(lang, 'adminOnly'));
+            }
+            console.log("Announcement from " + msg.from.id);
+            return broadcast(props.match[1])
+                .then(([delivered, total]) => send(msg.chat.id, Messages.text(lang, 'announceReport', delivered, total)));
+        })
+        .catch(failed(msg, "announcing"));
+});
+
+bot.on(/^\/history( \d+)?$/, (msg, props) => {
+    sendHistory(msg, props.match[1] ? parseInt(props.match[1]) : 10, null);
+});
+
+bot.on(/^\/limit_history( \d+)?$/, (msg, props) => {
+    sendHistory(msg, props.match[1] ? parseInt(props.match[1]) : 10, 'payLimit');
+});
+
+function sendHistory(msg, count, field) {
+    Promise.all([data.getHistory(msg.from.id, count, field), languageOf(msg.from)])
+        .then(([changes, lang]) => {
+            if (changes.length == 0) {
+                return send(msg.chat.id, Messages.text(lang, 'noHistory'));
+            }
+            const lines = changes.map(change => Messages.text(lang, 'historyEntry',
+                change.changedAt.toISOString().slice(0, 16).replace('T', ' '),
+                Messages.text(lang, change.field + 'Field'),
+                formatValue(change.oldValue), formatValue(change.newValue), Messages.text(lang, change.source + 'Source')));
+            return send(msg.chat.id, lines.join("\n"));
+        })
+        .catch(failed(msg, "getting history"));
+}
+
+bot.on('/backup', (msg) => {
+    if (!isAdmin(msg.from)) {
+        languageOf(msg.from)
+            .then(lang => send(msg.chat.id, Messages.text(lang, 'adminOnly')))
+            .catch(err => console.log("Error refusing backup", err));
+        return;
+    }
+    sendBackup([msg.chat.id]);
+});
+
+bot.on(/^\/prices (\d{5})( \w+)?$/, (msg, props) => {
+    const fuel = props.match[2] ? props.match[2].trim() : '95';
+    Promise.all([prices.cheapest(props.match[1], fuel, 5), languageOf(msg.from)])
+        .then(([stations, lang]) => {
+            if (stations === null) {
+                return send(msg.chat.id, Messages.text(lang, 'unknownFuel', Prices.fuels.join(', ')));
+            }
+            if (stations.length == 0) {
+                return send(msg.chat.id, Messages.text(lang, 'noStations', props.match[1]));
+            }
+            return averageComparison(msg.from.id, lang, stations[0].price)
+                .then(comparison => send(msg.chat.id, stations
+                    .map(station => "<b>" + round(station.price, 3) + "</b>/L " +
+                        Messages.escape(station.name) + " - " + Messages.escape(station.address))
+                    .join("\n") + comparison));
+        })
+        .catch(failed(msg, "getting prices"));
+});
+
+//Users who recorded liters see how their average price compares with the cheapest station
+function averageComparison(userId, lang, cheapest) {
+    return data.getSettings(userId)
+        .then(settings => settings && Promise.all([data.getLiters(userId), data.getPaidWithLiters(userId)]))
+        .then(fuel => {
+            if (!fuel || !(fuel[0] > 0)) {
+                return "";
+            }
+            const average = fuel[1] / fuel[0];
+            return "\n" + (average > cheapest ?
+                Messages.text(lang, 'averageAbove', round(average, 3), round(average - cheapest, 3)) :
+                Messages.text(lang, 'averageBelow', round(average, 3)));
+        });
+}
+
+bot.on('/users', (msg) => {
+    if (!isAdmin(msg.from)) {
+        languageOf(msg.from)
+            .then(lang => send(msg.chat.id, Messages.text(lang, 'adminOnly')))
+            .catch(err => console.log("Error refusing users", err));
+        return;
+    }
+    Promise.all([data.getUsersOverview(), languageOf(msg.from)])
+        .then(([users, lang]) => {
+            const lines = users.map(user => Messages.text(lang, 'userEntry',
+                user.username ? '@' + user.username : user.userId, user.chatId,
+                round(user.paid, 2), round(user.payLimit, 2),
+                user.lastActivity ? user.lastActivity.toISOString().slice(0, 10) : '-'));
+            return send(msg.chat.id, Messages.text(lang, 'usersTitle', users.length) + "\n" + lines.join("\n"));
+        })
+        .catch(failed(msg, "listing users"));
+});
+
+bot.on('/export_all', (msg) => {
+    Promise.all([data.getUserData(msg.from.id), languageOf(msg.from)])
+        .then(([userData, lang]) => {
+            if (!userData) {
+                return send(msg.chat.id, Messages.text(lang, 'notRegistered'));
+            }
+            return bot.sendDocument(msg.chat.id, Buffer.from(JSON.stringify(userData, null, 2)), {fileName: "fuel_expenses.json"});
+        })
+        .catch(failed(msg, "exporting"));
+});
+
+bot.on('/forget', (msg) => {
+    languageOf(msg.from)
+        .then(lang => {
+            const replyMarkup = bot.inlineKeyboard([[
+                bot.inlineButton(Messages.text(lang, 'forgetConfirm'), {callback: 'forget'}),
+                bot.inlineButton(Messages.text(lang, 'cancel'), {callback: 'cancel'})
+            ]]);
+            return send(msg.chat.id, Messages.text(lang, 'forgetQuestion'), {replyMarkup});
+        })
+        .catch(failed(msg, "asking forget confirmation"));
+});
+
+bot.on('callbackQuery', (msg) => {
+    const chatId = msg.message.chat.id;
+    const messageId = msg.message.message_id;
+    //Errors are answered in the chat of the button
+    const origin = {from: msg.from, chat: msg.message.chat};
+    const refusal = isAllowed(msg.from) ? buttonRefusal(msg.data, msg.from.id) : null;
+    if(refusal) {
+        languageOf(msg.from)
+            .then(lang => bot.answerCallbackQuery(msg.id, {text: Messages.text(lang, refusal)}))
+            .catch(err => console.log("Error refusing button", err));
+        return;
+    }
+    bot.answerCallbackQuery(msg.id);
+    if(!isAllowed(msg.from)) {
+        console.log("Refusing callback from " + msg.from.id);
+    } else if(msg.data.startsWith('reset:')) {
+        languageOf(msg.from)
+            .then(lang => data.reset(msg.from.id)
+                .then(() => edit(chatId, messageId, Messages.text(lang, 'resetDone'))))
+            .then(() => sendData(msg.from.id, chatId))
+            .catch(failed(origin, "resetting"));
+    } else if(msg.data == 'forget') {
+        console.log("Forgetting user "+msg.from.id);
+        //Language has to be read before the user is deleted
+        languageOf(msg.from)
+            .then(lang => data.forget(msg.from.id)
+                .then(() => edit(chatId, messageId, Messages.text(lang, 'forgetDone'))))
+            .catch(failed(origin, "forgetting"));
+    } else if(msg.data.startsWith('force:')) {
+        const [amount, liters] = msg.data.split(':').slice(2).map(parseFloat);
+        console.log("Adding " + amount + " over the limit for " + msg.from.id);
+        bot.editMessageReplyMarkup({chatId, messageId}, {replyMarkup: null})
+            .catch(err => console.log("Error removing add anyway", err));
+        //The button message is marked as processed so tapping it twice adds the expense once
+        addExpense(Object.assign({message_id: messageId}, origin), amount, liters, true);
+    } else if(msg.data.startsWith('undo:')) {
+        const [amount, liters] = msg.data.split(':').slice(3).map(parseFloat);
+        //Like Add anyway, each undo button works only once
+        Promise.all([data.markProcessed(chatId, messageId), languageOf(msg.from)])
+            .then(([first, lang]) => {
+                if (!first) {
+                    console.log("Skipping already undone message " + messageId + " of " + chatId);
+                    return;
+                }
+                return data.removeAmount(msg.from.id, amount, liters)
+                    .then(() => Webhook.notify('expenseUndone', {userId: msg.from.id, username: msg.from.username, amount, liters}))
+                    .then(() => edit(chatId, messageId, Messages.text(lang, 'undone', round(amount, 2))))
+                    .then(() => sendData(msg.from.id, chatId));
+            })
+            .catch(failed(origin, "undoing"));
+    } else if(msg.data == 'check') {
+        sendData(msg.from.id, chatId)
+            .catch(failed(origin, "checking"));
+    } else if(msg.data.split(':')[0] == 'cancel') {
+        languageOf(msg.from)
+            .then(lang => edit(chatId, messageId, Messages.text(lang, 'cancelled')))
+            .catch(failed(origin, "cancelling"));
+    }
+});
+
+bot.on('/help', (msg) => {
+    languageOf(msg.from)
+        .then(lang => send(msg.chat.id, Messages.text(lang, 'help')))
+        .catch(failed(msg, "sending help"));
 });
 
 bot.on('/check', (msg) => {
-    sendData(msg);
+    sendData(msg.from.id, msg.chat.id)
+        .catch(failed(msg, "checking"));
+});
+
+//Inline queries answer with the balance so users can post it in any chat
+bot.on('inlineQuery', (msg) => {
+    if (!isAllowed(msg.from)) {
+        return;
+    }
+    return Promise.all([data.getSettings(msg.from.id), languageOf(msg.from)])
+        .then(async ([settings, lang]) => {
+            if (!settings) {
+                return bot.answerQuery(bot.answerList(msg.id, {cacheTime: 0, personal: true,
+                    pmText: Messages.text(lang, 'inlineNotStarted'), pmParameter: 'start'}));
+            }
+            const answers = bot.answerList(msg.id, {cacheTime: 0, personal: true});
+            const spent = await data.getAmount(msg.from.id);
+            const left = round(settings['payLimit'] - spent, 2);
+            answers.addArticle({
+                id: 'balance',
+                title: Messages.text(lang, 'inlineTitle', left),
+                description: Messages.text(lang, 'inlineDescription', round(spent, 2), round(settings['payLimit'], 2)),
+                message_text: Messages.text(lang, 'inlineSummary', round(spent, 2), round(settings['payLimit'], 2), left),
+                parse_mode: 'html'
+            });
+            return bot.answerQuery(answers);
+        });
 });
 
-bot.on(/^\d+\.*\d*$/, (msg) => {
-    data.addAmount(msg.from.username, parseFloat(msg.text))
-        .then(added => {
-            if (added == -1) {
-                bot.sendMessage(msg.chat.id, "Expense exceeds limit!");
+bot.on(numberPattern, (msg) => {
+    languageOf(msg.from)
+        .then(lang => addExpense(msg, Text.parseAmount(msg.text, lang), 0))
+        .catch(failed(msg, "parsing amount"));
+});
+
+bot.on(litersPattern, (msg, props) => {
+    languageOf(msg.from)
+        .then(lang => {
+            const liters = Text.parseLiters(props.match[2]);
+            if (!Text.validLiters(liters, maxLiters())) {
+                return send(msg.chat.id, Messages.text(lang, 'invalidLiters', maxLiters()), replyTo(msg));
             }
-            sendData(msg);
+            addExpense(msg, Text.parseAmount(props.match[1], lang), liters);
         })
-        .catch(err => console.log("Error adding amount", err));
+        .catch(failed(msg, "parsing amount"));
+});
+
+bot.on(/^\/refund (\d[\d.,]*)$/, (msg, props) => {
+    languageOf(msg.from)
+        .then(lang => {
+            const amount = Text.parseAmount(props.match[1], lang);
+            if (!validAmount(amount)) {
+                return sendInvalidAmount(msg);
+            }
+            //Like expenses, a refund redelivered after a restart is deducted once
+            return data.markProcessed(msg.chat.id, msg.message_id)
+                .then(first => {
+                    if (!first) {
+                        console.log("Skipping already processed message " + msg.message_id + " of " + msg.chat.id);
+                        return;
+                    }
+                    return refund(msg, amount, lang);
+                });
+        })
+        .catch(failed(msg, "refunding"));
+});
+
+function refund(msg, amount, lang) {
+    console.log("Refunding " + amount + " to " + msg.from.id);
+    return data.refund(msg.from.id, amount)
+        .catch(unmarkProcessed(msg))
+        .then(() => Webhook.notify('refund', {userId: msg.from.id, username: msg.from.username, amount}))
+        .then(() => send(msg.chat.id, Messages.text(lang, 'refunded', round(amount, 2)), replyTo(msg)))
+        .then(() => sendData(msg.from.id, msg.chat.id, null, null, replyTo(msg)));
+}
+
+bot.on(/^\/recurring( .*)?$/, (msg, props) => {
+    const args = props.match[1] ? props.match[1].trim().split(' ') : ['list'];
+    languageOf(msg.from)
+        .then(async lang => {
+            const amount = Text.parseAmount(args[1], lang);
+            if (args[0] == 'add' && validAmount(amount) && validRecurringDay(args[2], args[3])) {
+                console.log("Adding recurring expense for " + msg.from.id + ": " + args.slice(1).join(' '));
+                await data.addRecurring(msg.from.id, amount, args[2], parseInt(args[3]));
+                return send(msg.chat.id, Messages.text(lang, 'recurringAdded'));
+            } else if (args[0] == 'remove' && /^\d+$/.test(args[1])) {
+                const removed = await data.removeRecurring(msg.from.id, parseInt(args[1]));
+                return send(msg.chat.id, Messages.text(lang, removed ? 'recurringRemoved' : 'recurringNotFound', args[1]));
+            } else if (args[0] == 'list') {
+                const recurring = await data.getRecurring(msg.from.id);
+                if (recurring.length == 0) {
+                    return send(msg.chat.id, Messages.text(lang, 'recurringEmpty'));
+                }
+                return send(msg.chat.id, recurring.map(expense => "#" + expense.id + " " + Messages.text(lang,
+                    expense.cadence == 'monthly' ? 'recurringMonthly' : 'recurringWeekly', round(expense.amount, 2), expense.day))
+                    .join("\n"));
+            }
+            return send(msg.chat.id, Messages.text(lang, 'recurringUsage'));
+        })
+        .catch(failed(msg, "handling recurring expenses"));
+});
+
+bot.on(negativePattern, (msg) => {
+    sendInvalidAmount(msg);
+});
+
+//Anything not a command or an expense gets a hint, at most once per cooldown to avoid noise in groups
+bot.on('text', (msg) => {
+    if (msg.text.startsWith('/') || [numberPattern, litersPattern, negativePattern].some(pattern => pattern.test(msg.text))) {
+        return;
+    }
+    const now = Date.now();
+    if (now - (hinted.get(msg.chat.id) || 0) < hintCooldown) {
+        return;
+    }
+    hinted.set(msg.chat.id, now);
+    return languageOf(msg.from)
+        .then(lang => send(msg.chat.id, Messages.text(lang, 'hint'), replyTo(msg)));
 });
 
 bot.on(/^\/config (.+)$/, (msg, props) => {
     const propsText = props.match[1].split(' ');
     if(propsText[0] == 'limit') {
-        console.log("Configuring limit for "+msg.from.username+" to: "+propsText[1]);
-        data.setLimit(msg.from.username, parseFloat(propsText[1]))
-            .then(() => sendData(msg))
-            .catch(err => console.log("Error configuring limit for "+msg.from.username+" "+err));
+        const thisMonthOnly = propsText[2] == '--this-month-only';
+        languageOf(msg.from)
+            .then(lang => {
+                const limit = Text.parseAmount(propsText[1] || '', lang);
+                if(!validAmount(limit)) {
+                    return sendInvalidAmount(msg);
+                }
+                console.log("Configuring limit for "+msg.from.id+" to: "+limit+(thisMonthOnly ? " this month" : ""));
+                return data.setLimit(msg.from.id, limit, thisMonthOnly)
+                    .then(() => thisMonthOnly && send(msg.chat.id, Messages.text(lang, 'limitThisMonth', round(limit, 2))))
+                    .then(() => sendData(msg.from.id, msg.chat.id));
+            })
+            .catch(failed(msg, "configuring limit for " + msg.from.id));
+    } else if(propsText[0] == 'warn') {
+        const percent = /^\d+(\.\d+)?$/.test(propsText[1]) ? parseFloat(propsText[1]) : NaN;
+        if(!(percent >= 0 && percent <= 100)) {
+            languageOf(msg.from)
+                .then(lang => send(msg.chat.id, Messages.text(lang, 'warnUsage')))
+                .catch(failed(msg, "configuring warning for " + msg.from.id));
+            return;
+        }
+        console.log("Configuring warning for "+msg.from.id+" to: "+percent+"%");
+        data.setWarnPercent(msg.from.id, percent)
+            .then(() => languageOf(msg.from))
+            .then(lang => send(msg.chat.id, Messages.text(lang, 'warnConfigured', propsText[1])))
+            .catch(failed(msg, "configuring warning for " + msg.from.id));
+    } else if(propsText[0] == 'language') {
+        const lang = Messages.supported(propsText[1]);
+        if(!lang) {
+            languageOf(msg.from)
+                .then(current => send(msg.chat.id, Messages.text(current, 'unknownLanguage', Messages.languages.join(', '))))
+                .catch(failed(msg, "configuring language for " + msg.from.id));
+            return;
+        }
+        console.log("Configuring language for "+msg.from.id+" to: "+lang);
+        data.setLanguage(msg.from.id, lang)
+            .then(() => send(msg.chat.id, Messages.text(lang, 'languageConfigured')))
+            .catch(failed(msg, "configuring language for " + msg.from.id));
+    } else if(propsText[0] == 'autoreset' && (propsText[1] == 'on' || propsText[1] == 'off')) {
+        console.log("Configuring auto reset for "+msg.from.id+" to: "+propsText[1]);
+        data.setAutoReset(msg.from.id, propsText[1] == 'on')
+            .then(() => languageOf(msg.from))
+            .then(lang => send(msg.chat.id, Messages.text(lang, propsText[1] == 'on' ? 'autoResetOn' : 'autoResetOff')))
+            .catch(failed(msg, "configuring auto reset for " + msg.from.id));
+    } else if(propsText[0] == 'quickadd') {
+        configureQuickAdd(msg, propsText.slice(1));
+    } else if(propsText[0] == 'notifications') {
+        configureNotifications(msg, propsText[1], propsText[2]);
     } else {
         console.log("Unknown config: "+ propsText[0]);
     }
 });
 
+//Shows the amounts as a reply keyboard, or removes it when there are none
+function configureQuickAdd(msg, amounts) {
+    languageOf(msg.from)
+        .then(async lang => {
+            if (amounts.length > 8 || !amounts.every(amount => validAmount(Text.parseAmount(amount, lang)))) {
+                return send(msg.chat.id, Messages.text(lang, 'quickAddUsage'));
+            }
+            console.log("Configuring quick add for "+msg.from.id+" to: "+amounts.join(' '));
+            await data.setQuickAdd(msg.from.id, amounts.length ? amounts.join(' ') : null);
+            if (amounts.length == 0) {
+                return send(msg.chat.id, Messages.text(lang, 'quickAddRemoved'), {replyMarkup: 'hide'});
+            }
+            return send(msg.chat.id, Messages.text(lang, 'quickAddConfigured'),
+                {replyMarkup: bot.keyboard([amounts], {resize: true})});
+        })
+        .catch(failed(msg, "configuring quick add for " + msg.from.id));
+}
+
+//Shows the notification settings after switching the given kind on or off, if any
+function configureNotifications(msg, kind, state) {
+    languageOf(msg.from)
+        .then(async lang => {
+            if (kind !== undefined) {
+                const known = (state == 'on' || state == 'off') &&
+                    await data.setNotification(msg.from.id, kind, state == 'on');
+                if (!known) {
+                    return send(msg.chat.id, Messages.text(lang, 'notificationsUsage'));
+                }
+                console.log("Configuring " + kind + " notifications for " + msg.from.id + " to: " + state);
+            }
+            const notifications = await data.getNotifications(msg.from.id);
+            const onOff = enabled => Messages.text(lang, enabled ? 'on' : 'off');
+            return send(msg.chat.id, Messages.text(lang, 'notificationsStatus',
+                onOff(notifications.summary), onOff(notifications.announcements), onOff(notifications.warnings),
+                onOff(notifications.reminders)));
+        })
+        .catch(failed(msg, "configuring notifications for " + msg.from.id));
+}
+
+function addExpense(msg, amount, liters, force = false) {
+    if (!validAmount(amount) || !(liters >= 0)) {
+        sendInvalidAmount(msg);
+        return;
+    }
+    data.markProcessed(msg.chat.id, msg.message_id)
+        .then(first => {
+            if (!first) {
+                console.log("Skipping already processed message " + msg.message_id + " of " + msg.chat.id);
+                return;
+            }
+            return data.addAmount(msg.from.id, amount, liters, force)
+                .catch(unmarkProcessed(msg))
+                .then(added => expenseAdded(msg, amount, liters, force, added));
+        })
+        .catch(failed(msg, "adding amount"));
+}
+
+//Rejects again with the error once the message is no longer marked as processed
+function unmarkProcessed(msg) {
+    return err => data.unmarkProcessed(msg.chat.id, msg.message_id)
+        .catch(unmarkErr => console.log("Error unmarking message " + msg.message_id + " of " + msg.chat.id, unmarkErr))
+        .then(() => Promise.reject(err));
+}
+
+function expenseAdded(msg, amount, liters, force, added) {
+    if (added == -1) {
+        Webhook.notify('limitExceeded', {userId: msg.from.id, username: msg.from.username, amount, liters});
+        languageOf(msg.from)
+            .then(lang => send(msg.chat.id, Messages.text(lang, 'limitExceeded'), Object.assign({
+                replyMarkup: bot.inlineKeyboard([[
+                    bot.inlineButton(Messages.text(lang, 'addAnyway'), {callback: 'force:' + msg.from.id + ':' + amount + ':' + liters})
+                ]])
+            }, replyTo(msg))))
+            .catch(err => console.log("Error sending limit exceeded", err));
+        return sendData(msg.from.id, msg.chat.id, null, null, replyTo(msg));
+    } else {
+        Webhook.notify('expenseAdded', {userId: msg.from.id, username: msg.from.username, amount, liters, total: added, forced: force});
+        checkThreshold(msg.from.id, msg.chat.id, added - amount, added);
+        return sendData(msg.from.id, msg.chat.id, null, (lang) => bot.inlineKeyboard([[
+            bot.inlineButton(Messages.text(lang, 'undo'), {callback: 'undo:' + msg.from.id + ':' + monthKey(new Date()) + ':' + amount + ':' + liters}),
+            bot.inlineButton(Messages.text(lang, 'check'), {callback: 'check'})
+        ]]), replyTo(msg));
+    }
+}
+
+//Buttons changing the amounts carry the user they were sent to, undo also the month as the amounts may have been reset since
+function buttonRefusal(callback, userId) {
+    const [action, owner, month] = callback.split(':');
+    if (owner !== undefined && ['undo', 'force', 'reset', 'cancel'].includes(action) && owner != userId) {
+        return 'notYourButton';
+    }
+    if (action == 'undo' && month != monthKey(new Date())) {
+        return 'staleButton';
+    }
+    return null;
+}
+
+function monthKey(date) {
+    return date.getFullYear() * 100 + date.getMonth() + 1;
+}
+
+function validAmount(amount) {
+    return Text.validAmount(amount, maxAmount());
+}
+
+function maxAmount() {
+    return config.app.maxAmount || 10000;
+}
+
+function maxLiters() {
+    return config.app.maxLiters || 150;
+}
+
+function sendInvalidAmount(msg) {
+    languageOf(msg.from)
+        .then(lang => send(msg.chat.id, Messages.text(lang, 'invalidAmount', maxAmount()), replyTo(msg)))
+        .catch(err => console.log("Error sending invalid amount", err));
+}
+
+function checkThreshold(userId, chatId, previous, current) {
+    Promise.all([data.getLimit(userId), data.getWarnPercent(userId), data.getNotifications(userId), languageOf({id: userId})])
+        .then(([limit, percent, notifications, lang]) => {
+            const threshold = limit * percent / 100;
+            if (notifications.warnings && percent > 0 && previous < threshold && current >= threshold) {
+                return send(chatId, Messages.text(lang, 'warnThreshold', round(current * 100 / limit, 0)));
+            }
+        })
+        .catch(err => console.log("Error checking threshold", err));
+}
+
+function isLastDayOfMonth(date) {
+    return new Date(date.getFullYear(), date.getMonth(), date.getDate() + 1).getDate() == 1;
+}
+
+function sendMonthlySummaries() {
+    data.getAllUsers()
+        .then(users => users.filter(user => user.notifySummary)
+            .forEach(user => sendData(user.userId, user.chatId, 'monthlySummary')
+                .catch(err => {
+                    console.log("Error sending monthly summary to " + user.userId, err);
+                    deactivateIfBlocked(user.userId, err);
+                })))
+        .catch(err => console.log("Error sending monthly summaries", err));
+}
+
+//Starts the month unless already done, also on startup in case the bot was stopped over the first of the month
+function catchUpMonth() {
+    const month = String(monthKey(new Date()));
+    return data.getState('monthStarted')
+        .then(started => {
+            if (started === null) {
+                //Nothing to catch up with on a new database
+                return data.setState('monthStarted', month);
+            }
+            if (started != month) {
+                console.log("Starting month " + month + ", last started " + started);
+                return startMonth(month);
+            }
+        })
+        .catch(err => console.log("Error checking month", err));
+}
+
+//Resets the amount of the users with auto reset, letting them know what they spent
+function startMonth(month) {
+    return data.restoreLimits()
+        .then(() => data.getUsersToReset())
+        .then(users => users.forEach(user => data.reset(user.userId, true)
+            .then(() => languageOf({id: user.userId}))
+            .then(lang => user.notifySummary && user.active && send(user.chatId,
+                Messages.text(lang, 'newMonth', round(user.payLimit, 2), round(user.paid, 2))))
+            .catch(err => {
+                console.log("Error starting month for " + user.userId, err);
+                deactivateIfBlocked(user.userId, err);
+            })))
+        .then(() => data.setState('monthStarted', month))
+        .catch(err => console.log("Error starting month", err));
+}
+
+function validRecurringDay(cadence, day) {
+    const max = {monthly: 31, weekly: 7}[cadence];
+    return max !== undefined && /^\d+$/.test(day) && parseInt(day) >= 1 && parseInt(day) <= max;
+}
+
+//Days beyond the end of the month are recorded on its last day
+function isRecurringDue(expense, date) {
+    if (expense.cadence == 'weekly') {
+        return expense.day == (date.getDay() || 7);
+    }
+    const lastDay = new Date(date.getFullYear(), date.getMonth() + 1, 0).getDate();
+    return Math.min(expense.day, lastDay) == date.getDate();
+}
+
+//Recurring expenses are fixed costs so they are recorded even over the limit, and for inactive users without telling them
+function addRecurringExpenses() {
+    const today = new Date();
+    data.getAllRecurring()
+        .then(recurring => recurring.filter(expense => isRecurringDue(expense, today))
+            .forEach(expense => data.addRecurringAmount(expense.userId, expense.amount)
+                .then(total => Webhook.notify('expenseAdded', {userId: expense.userId, username: expense.username,
+                    amount: expense.amount, liters: 0, total, recurring: true}))
+                .then(() => expense.active && languageOf({id: expense.userId})
+                    .then(lang => send(expense.chatId, Messages.text(lang, 'recurringRecorded', round(expense.amount, 2))))
+                    .then(() => sendData(expense.userId, expense.chatId)))
+                .catch(err => {
+                    console.log("Error adding recurring expense " + expense.id, err);
+                    deactivateIfBlocked(expense.userId, err);
+                })))
+        .catch(err => console.log("Error adding recurring expenses", err));
+}
+
+function sendReminders(days) {
+    data.getUsersToRemind(days)
+        .then(users => users.forEach(user => languageOf({id: user.userId})
+            .then(lang => send(user.chatId, Messages.text(lang, 'reminder', days)))
+            .catch(err => {
+                console.log("Error reminding " + user.userId, err);
+                deactivateIfBlocked(user.userId, err);
+            })))
+        .catch(err => console.log("Error sending reminders", err));
+}
+
+//Every user is allowed unless a list of ids or usernames is configured
+function isAllowed(user) {
+    const allowed = config.app.allowedUsers;
+    return !allowed || allowed.includes(user.id) || (user.username !== undefined && allowed.includes(user.username));
+}
+
+function isAdmin(user) {
+    return (config.app.admins || []).includes(user.id);
+}
+
+//Telegram answers 403 when the user blocked the bot or deleted the account
+function deactivateIfBlocked(userId, err) {
+    if (err.error_code != 403) {
+        return;
+    }
+    console.log("Deactivating " + userId + ": " + err.description);
+    data.setActive(userId, false)
+        .catch(err => console.log("Error deactivating " + userId, err));
+}
+
+//Sends the text to every registered chat accepting announcements one by one, resolving to the number of deliveries and chats
+async function broadcast(text) {
+    const users = (await data.getAllUsers()).filter(user => user.notifyAnnouncements);
+    var delivered = 0;
+    for (const user of users) {
+        try {
+            await throttled(() => bot.sendMessage(user.chatId, text));
+            delivered++;
+        } catch (err) {
+            console.log("Error broadcasting to " + user.chatId, err);
+            deactivateIfBlocked(user.userId, err);
+        }
+    }
+    return [delivered, users.length];
+}
+
+function sendBackup(chatIds) {
+    data.dump()
+        .then(tables => {
+            const backup = zlib.gzipSync(JSON.stringify(tables));
+            const fileName = "fuel_expense_bot-" + new Date().toISOString().slice(0, 10) + ".json.gz";
+            return Promise.all(chatIds.map(chatId => throttled(() => bot.sendDocument(chatId, backup, {fileName}))));
+        })
+        .catch(err => console.log("Error sending backup", err));
+}
+
+//Registers the command menus, admin commands are only shown in the private chats of the admins
+function setCommands() {
+    const userCommands = ['start', 'check', 'refund', 'recurring', 'reset', 'undo', 'history', 'limit_history', 'prices', 'export_all', 'forget', 'help'];
+    const adminCommands = userCommands.concat(['announce', 'backup', 'users']);
+    const requests = [];
+    [null].concat(Messages.languages).forEach(lang => {
+        const options = lang ? {language_code: lang} : {};
+        requests.push(bot.request('/setMyCommands', Object.assign({
+            commands: JSON.stringify(Messages.commandList(lang || 'en', userCommands))
+        }, options)));
+        (config.app.admins || []).forEach(admin => requests.push(bot.request('/setMyCommands', Object.assign({
+            commands: JSON.stringify(Messages.commandList(lang || 'en', adminCommands)),
+            scope: JSON.stringify({type: 'chat', chat_id: admin})
+        }, options))));
+    });
+    Promise.all(requests)
+        .then(() => console.log("Bot commands registered"))
+        .catch(err => console.log("Error registering bot commands", err));
+}
+
+function sleep(milliseconds) {
+    return new Promise(resolve => setTimeout(resolve, milliseconds));
+}
+
+//Every call to Telegram waits for the previous one plus an interval, to stay below its flood limits
+var lastCall = Promise.resolve();
+
+function throttled(call) {
+    const result = lastCall.then(() => retryTooManyRequests(call));
+    lastCall = result.catch(() => {}).then(() => sleep(config.app.sendInterval || 35));
+    return result;
+}
+
+async function retryTooManyRequests(call) {
+    for (let attempt = 1; ; attempt++) {
+        try {
+            return await call();
+        } catch (err) {
+            if (err.error_code != 429 || attempt >= 3) {
+                throw err;
+            }
+            const wait = (err.parameters && err.parameters.retry_after) || 1;
+            console.log("Too many requests to Telegram, retrying in " + wait + "s");
+            await sleep(wait * 1000);
+        }
+    }
+}
+
+//Texts over the Telegram limit are sent in several messages, with the buttons in the last one
+async function send(chatId, text, options = {}) {
+    const parts = Text.split(text, maxMessageLength);
+    const rest = Object.assign({}, options);
+    delete rest.replyMarkup;
+    let sent;
+    for (let i = 0; i < parts.length; i++) {
+        const partOptions = i == parts.length - 1 ? options : rest;
+        sent = await throttled(() => bot.sendMessage(chatId, parts[i], Object.assign({parseMode: 'html'}, partOptions)));
+    }
+    return sent;
+}
+
+
+//Logs the error and tells the user something went wrong instead of leaving them without an answer
+function failed(msg, action) {
+    return err => {
+        console.log("Error " + action, err);
+        handlerErrors++;
+        if (!msg.from) {
+            return;
+        }
+        //The error may come from the database, then the language of the client is used
+        languageOf(msg.from)
+            .catch(() => msg.from.language_code)
+            .then(lang => msg.chat ? send(msg.chat.id, Messages.text(lang, 'somethingWrong'), replyTo(msg)) :
+                //Inline queries have no chat, the error is shown instead of the results
+                bot.answerQuery(bot.answerList(msg.id, {cacheTime: 0, personal: true,
+                    pmText: Messages.text(lang, 'somethingWrong'), pmParameter: 'start'})))
+            .catch(err => console.log("Error sending error reply", err));
+    };
+}
+
+//Answers in reply to the message, which in forum groups also keeps the answer in its topic
+//Messages built from button presses without an id are sent without reply
+function replyTo(msg) {
+    return msg.message_id ? {replyToMessage: msg.message_id} : {};
+}
+
+function edit(chatId, messageId, text) {
+    return throttled(() => bot.editMessageText({chatId, messageId}, text, {parseMode: 'html'}));
+}
+
+//Stored language of the user, falling back to the one of its Telegram client
+function languageOf(user) {
+    return data.getLanguage(user.id)
+        .then(lang => lang || user.language_code);
+}
+
+function formatValue(value) {
+    if (value === null) {
+        return "-";
+    }
+    return isNaN(value) ? value : round(value, 2);
+}
+
 function round(value, decimals) {
     return Number(Math.round(value +'e'+ decimals) +'e-'+ decimals).toFixed(decimals);
 }
 
-function sendData(msg) {
-    data.getAmount(msg.from.username)
-        .then(num => {
-            var rounded = round(num, 2);
-            data.getLimit(msg.from.username)
-            .then(limit =>
-                bot.sendMessage(msg.chat.id,
-                    "Spent: " + rounded.toString() + "\n" +
-                    "Left: " + round(limit - num, 2))
-            );
-        })
-        .catch(err => console.log("Error getting amount", err));
+//Only amounts reset every month can be compared with the time elapsed
+function pace(lang, spent, limit) {
+    const today = new Date();
+    const days = new Date(today.getFullYear(), today.getMonth() + 1, 0).getDate();
+    const monthPercent = today.getDate() * 100 / days;
+    const limitPercent = spent * 100 / limit;
+    return Messages.text(lang, 'pace', today.getDate(), days, round(monthPercent, 0), round(limitPercent, 0)) +
+        (limitPercent > monthPercent ? " ⚠️" : "");
 }
 
-process.on('SIGINT', function() {
-    console.log("Caught interrupt signal");
+//Actions builds the inline keyboard to attach, given the language of the user
+function sendData(userId, chatId, title, actions, options = {}) {
+    return Promise.all([data.getAmount(userId), data.getLimit(userId), data.getLiters(userId), data.getPaidWithLiters(userId),
+        data.getAutoReset(userId), languageOf({id: userId})])
+        .then(([num, limit, liters, paidWithLiters, autoReset, lang]) => {
+            var text = (title ? Messages.text(lang, title) + "\n" : "") +
+                Messages.text(lang, 'spent', round(num, 2)) + "\n" +
+                Messages.text(lang, 'left', round(limit - num, 2));
+            if (liters > 0) {
+                text += "\n" + Messages.text(lang, 'liters', round(liters, 2)) + "\n" +
+                    Messages.text(lang, 'averagePrice', round(paidWithLiters / liters, 3));
+            }
+            if (autoReset && limit > 0) {
+                text += "\n" + pace(lang, num, limit);
+            }
+            return send(chatId, text, Object.assign(actions ? {replyMarkup: actions(lang)} : {}, options));
+        });
+}
 
+function shutdown(signal) {
+    console.log("Caught " + signal + " signal");
+
+    scheduler.stop();
+    if (health) {
+        health.close();
+    }
     data.close();
     bot.stop(); //Seems it takes enough time for the DB to close
+}
+
+//SIGTERM is what docker sends on stop
+process.on('SIGINT', shutdown);
+process.on('SIGTERM', shutdown);
+
+//Hour of the scheduled notifications, summaryHour in the config
+const summaryHour = config.app.summaryHour !== undefined ? config.app.summaryHour : 20;
+
+scheduler.daily('monthly-summary', summaryHour, () => {
+    if (isLastDayOfMonth(new Date())) {
+        sendMonthlySummaries();
+    }
+});
+
+scheduler.daily('new-month', 0, catchUpMonth);
+
+//After the new month has started so the amounts are not reset
+scheduler.daily('recurring', 1, addRecurringExpenses);
+
+scheduler.daily('processed-cleanup', 0, () => {
+    data.cleanProcessed(7)
+        .catch(err => console.log("Error cleaning processed messages", err));
 });
 
-bot.start();
\ No newline at end of file
+if (config.app.reminderDays) {
+    scheduler.daily('reminders', summaryHour, () => sendReminders(config.app.reminderDays));
+}
+
+//Private chats with the admins share their ids
+if (config.app.backupHour !== undefined) {
+    scheduler.daily('backup', config.app.backupHour, () => sendBackup(config.app.admins || []));
+}
+
+//Every check fails if it does not finish in time, so an unreachable service does not hang the report
+function checkSetup() {
+    const checks = {
+        database: () => data.verify(),
+        telegram: () => bot.getMe().then(me => console.log("Telegram token belongs to @" + me.username))
+    };
+    const timeout = (config.app.startupTimeout || 30000) + 5000;
+    const names = Object.keys(checks);
+    Promise.allSettled(names.map(name => Promise.race([checks[name](),
+        sleep(timeout).then(() => Promise.reject(new Error("No answer after " + timeout / 1000 + "s")))])))
+        .then(results => {
+            results.forEach((result, i) => console.log("Check " + names[i] + ": " +
+                (result.status == 'fulfilled' ? "ok" : "failed, " + (result.reason.message || result.reason.description || result.reason))));
+            process.exit(results.every(result => result.status == 'fulfilled') ? 0 : 1);
+        });
+}
+
+if (checkOnly) {
+    checkSetup();
+} else {
+    data.verify()
+        .then(() => {
+            setCommands();
+            catchUpMonth();
+            bot.start();
+        })
+        .catch(err => {
+            console.log("DB self-check failed:", err.message);
+            process.exit(1);
+        });
+}
\ No newline at end of file
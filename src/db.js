@@ -1,14 +1,95 @@
-const config = require("./config.js");
+const config = require("./settings.js");
 const mariadb = require('mariadb');
+const fs = require('fs');
 require('log-timestamp');
 
+//Error codes worth retrying, besides the fatal ones that need a new connection
+const transientErrors = ['ECONNRESET', 'ETIMEDOUT', 'ER_LOCK_DEADLOCK', 'ER_LOCK_WAIT_TIMEOUT'];
+//Errors after which the statement surely was not applied
+const notAppliedErrors = ['ER_LOCK_DEADLOCK', 'ER_LOCK_WAIT_TIMEOUT'];
+
+//Writes may have been applied before the connection failed, so they are only retried when they surely were not
+function retryable(sql, err) {
+    if(err.unavailable || notAppliedErrors.includes(err.code)) {
+        return true;
+    }
+    return /^\s*SELECT/i.test(sql) && (err.fatal || transientErrors.includes(err.code));
+}
+
+//Tables with rows of each user, the ones referencing counts first
+const userTables = ['recurring', 'journal', 'audit', 'counts'];
+
+//Changes that can be undone, older journal rows are deleted
+const journalSize = 20;
+
+//Tables and columns the bot needs, as created by scripts/initdb.sql
+const schema = {
+    counts: ['userId', 'username', 'chatId', 'payLimit', 'baseLimit', 'warnPercent', 'language', 'notifySummary',
+        'notifyAnnouncements', 'notifyWarnings', 'notifyReminders', 'lastExpense', 'quickAdd', 'autoReset', 'active', 'paid', 'liters', 'paidWithLiters'],
+    journal: ['id', 'userId', 'paid', 'liters', 'paidWithLiters'],
+    audit: ['id', 'userId', 'field', 'oldValue', 'newValue', 'changedAt', 'source'],
+    processed: ['chatId', 'messageId', 'processedAt'],
+    recurring: ['id', 'userId', 'amount', 'cadence', 'day'],
+    state: ['name', 'value']
+};
+
+//Columns of counts for each kind of notification that can be switched off
+const notificationColumns = {
+    summary: 'notifySummary',
+    announcements: 'notifyAnnouncements',
+    warnings: 'notifyWarnings',
+    reminders: 'notifyReminders'
+};
+
+//Options from config.db, overridden by the DATABASE_URL, DB_PASSWORD_FILE and DB_SSL_CA_FILE environment variables when present
+function connectionOptions() {
+    //Telegram ids are BIGINT but always fit in a javascript number
+    const options = Object.assign({bigIntAsNumber: true}, config.db);
+    if(process.env.DATABASE_URL) {
+        Object.assign(options, parseUrl(process.env.DATABASE_URL));
+    }
+    if(process.env.DB_PASSWORD_FILE) {
+        options.password = fs.readFileSync(process.env.DB_PASSWORD_FILE, 'utf8').trim();
+    }
+    if(process.env.DB_SSL_CA_FILE) {
+        options.ssl = Object.assign({}, typeof options.ssl == 'object' ? options.ssl : {}, {ca: fs.readFileSync(process.env.DB_SSL_CA_FILE, 'utf8')});
+    }
+    return options;
+}
+
+function parseUrl(databaseUrl) {
+    const url = new URL(databaseUrl);
+    if(url.protocol != 'mariadb:' && url.protocol != 'mysql:') {
+        throw new Error("Unsupported DATABASE_URL protocol: " + url.protocol);
+    }
+    if(!url.hostname || url.pathname.length < 2) {
+        throw new Error("DATABASE_URL must include host and database");
+    }
+    const options = {
+        host: url.hostname,
+        port: url.port ? parseInt(url.port) : 3306,
+        user: decodeURIComponent(url.username),
+        password: decodeURIComponent(url.password),
+        database: decodeURIComponent(url.pathname.slice(1))
+    };
+    //?ssl=true connects with TLS verifying the server certificate
+    if(url.searchParams.get('ssl') == 'true') {
+        options.ssl = true;
+    }
+    return options;
+}
+
 class Db {
     constructor() {
+        this.options = connectionOptions();
+        this.settings = new Map();
+        this.metrics = {cacheHits: 0, cacheMisses: 0, queries: 0, queryErrors: 0, slowQueries: 0, queryTime: 0};
         this.loadConnection();    
     }
 
     loadConnection() {
-        mariadb.createConnection(config.db)
+        clearTimeout(this.check);
+        this.connecting = mariadb.createConnection(this.options)
             .then(conn => {
                 console.log("DB Connection established!");
                 this.conn = conn;
@@ -18,7 +99,51 @@ class Db {
                 console.log("DB Connection error:", err);
                 var that = this;
                 this.check = setTimeout(function() { that.checkConnection() }, config.app.pingInterval);
-            });
+            })
+            .finally(() => this.connecting = null);
+    }
+
+    //Runs the query retrying with exponential backoff on transient errors
+    //Times every query including its retries and logs those slower than slowQuery milliseconds
+    async query(sql, values) {
+        const start = Date.now();
+        this.metrics.queries++;
+        try {
+            return await this.execute(sql, values);
+        } catch(err) {
+            this.metrics.queryErrors++;
+            throw err;
+        } finally {
+            const elapsed = Date.now() - start;
+            this.metrics.queryTime += elapsed;
+            if(elapsed > (config.app.slowQuery || 1000)) {
+                this.metrics.slowQueries++;
+                console.warn("Slow DB query took " + elapsed + "ms:", sql);
+            }
+        }
+    }
+
+    async execute(sql, values) {
+        const attempts = config.app.retryAttempts || 3;
+        const delay = config.app.retryDelay || 500;
+        for(let attempt = 1; ; attempt++) {
+            try {
+                if(!this.conn) {
+                    throw Object.assign(new Error("DB connection not available"), {fatal: true, unavailable: true});
+                }
+                return await this.conn.query(sql, values);
+            } catch(err) {
+                //The connection is replaced even when the query is not retried
+                if(err.fatal && !this.connecting) {
+                    this.loadConnection();
+                }
+                if(!retryable(sql, err) || attempt >= attempts) {
+                    throw err;
+                }
+                console.log("DB query failed on attempt " + attempt + ", retrying:", err.message);
+                await new Promise(resolve => setTimeout(resolve, delay * 2 ** (attempt - 1)));
+            }
+        }
     }
 
     checkConnection() {
@@ -38,35 +163,412 @@ class Db {
         }
     }
 
-    start(user, id) {
-        return this.conn.query("INSERT INTO counts(username, chatId, paid) VALUES (?, ?, ?)", [user, id, 0]);
+    //Waits up to startupTimeout for the connection and checks the schema, logging the rows of every table and the schema version
+    async verify() {
+        const timeout = config.app.startupTimeout || 30000;
+        const deadline = Date.now() + timeout;
+        while(!this.conn) {
+            if(Date.now() >= deadline) {
+                throw new Error("Database unreachable after " + timeout / 1000 + "s");
+            }
+            await new Promise(resolve => setTimeout(resolve, 1000));
+        }
+        for(const table of Object.keys(schema)) {
+            try {
+                await this.query("SELECT " + schema[table].join(', ') + " FROM " + table + " LIMIT 0");
+            } catch(err) {
+                if(err.code == 'ER_NO_SUCH_TABLE') {
+                    throw new Error("Missing table " + table + ", create it with scripts/initdb.sql");
+                }
+                if(err.code == 'ER_BAD_FIELD_ERROR') {
+                    throw new Error("Outdated table " + table + ", apply scripts/migrations: " + err.text);
+                }
+                throw err;
+            }
+            const rows = await this.query("SELECT COUNT(*) AS count FROM " + table);
+            console.log("Table " + table + " has " + rows[0]['count'] + " rows");
+        }
+        //Names and values with emojis need utf8mb4
+        const tables = await this.query("SELECT TABLE_NAME, TABLE_COLLATION FROM information_schema.TABLES WHERE TABLE_SCHEMA = DATABASE() AND TABLE_NAME IN (?)",
+            [Object.keys(schema)]);
+        for(const table of tables) {
+            if(!table['TABLE_COLLATION'].startsWith('utf8mb4')) {
+                throw new Error("Table " + table['TABLE_NAME'] + " has collation " + table['TABLE_COLLATION'] + ", apply scripts/migrations to convert it to utf8mb4");
+            }
+        }
+        const version = await this.getState('schemaVersion');
+        console.log("Schema version " + (version || "unknown, apply scripts/migrations"));
+    }
+
+    ping() {
+        if(!this.conn) {
+            return Promise.reject(new Error("DB connection not available"));
+        }
+        return this.conn.ping();
+    }
+
+    //Does nothing for users already registered
+    async start(userId, username, chatId, language) {
+        //Users registered from a group before rows were keyed by user ID, see scripts/migrations/001-user-id.sql
+        if(username) {
+            await this.query("UPDATE counts SET userId = ? WHERE userId < 0 AND username = ?", [userId, username]);
+        }
+        const result = await this.query("INSERT INTO counts(userId, username, chatId, paid, language) VALUES (?, ?, ?, ?, ?) ON DUPLICATE KEY UPDATE userId = userId",
+            [userId, username, chatId, 0, language]);
+        if(result.affectedRows > 0) {
+            await this.audit(userId, 'user', null, username);
+        }
     }
     
-    async getAmount(user) {
-        const rows = await this.conn.query("SELECT paid FROM counts WHERE username = ?", [user]);
+    async getAmount(userId) {
+        const rows = await this.query("SELECT paid FROM counts WHERE userId = ?", [userId]);
         return rows[0]['paid'];
     }
 
-    async getLimit(user) {
-        const rows = await this.conn.query("SELECT payLimit FROM counts WHERE username = ?", [user]);
-        return rows[0]['payLimit'];
+    //Settings of the user are read on almost every message so they are cached until changed
+    async getSettings(userId) {
+        if(this.settings.has(userId)) {
+            this.metrics.cacheHits++;
+            return this.settings.get(userId);
+        }
+        this.metrics.cacheMisses++;
+        const rows = await this.query("SELECT chatId, active, payLimit, warnPercent, language, autoReset, notifySummary, notifyAnnouncements, notifyWarnings, notifyReminders " +
+            "FROM counts WHERE userId = ?",
+            [userId]);
+        if(rows.length) {
+            this.settings.set(userId, rows[0]);
+        }
+        return rows[0];
+    }
+
+    //Returns true if the stored chat was a different one
+    async updateChatId(userId, chatId) {
+        const settings = await this.getSettings(userId);
+        if(!settings || settings['chatId'] == chatId) {
+            return false;
+        }
+        await this.query("UPDATE counts SET chatId = ? WHERE userId = ?", [chatId, userId]);
+        this.settings.delete(userId);
+        return true;
+    }
+
+    //Inactive users have blocked the bot and get no notifications until they write to it again
+    async setActive(userId, active) {
+        await this.query("UPDATE counts SET active = ? WHERE userId = ?", [active, userId]);
+        this.settings.delete(userId);
+    }
+
+    //Returns true if the user was inactive
+    async reactivate(userId) {
+        const settings = await this.getSettings(userId);
+        if(!settings || settings['active']) {
+            return false;
+        }
+        await this.setActive(userId, true);
+        return true;
+    }
+
+    async getLimit(userId) {
+        const settings = await this.getSettings(userId);
+        return settings['payLimit'];
+    }
+
+    async getWarnPercent(userId) {
+        const settings = await this.getSettings(userId);
+        return settings['warnPercent'];
+    }
+
+    async setWarnPercent(userId, percent) {
+        const old = await this.getWarnPercent(userId);
+        await this.query("UPDATE counts SET warnPercent = ? WHERE userId = ?", [percent, userId]);
+        this.settings.delete(userId);
+        return this.audit(userId, 'warnPercent', old, percent);
+    }
+
+    //Map of notification kind to whether it is enabled
+    async getNotifications(userId) {
+        const settings = await this.getSettings(userId);
+        const notifications = {};
+        Object.keys(notificationColumns).forEach(kind => notifications[kind] = Boolean(settings[notificationColumns[kind]]));
+        return notifications;
     }
 
-    setLimit(user, newLimit) {
-        return this.conn.query("UPDATE counts SET payLimit = ? WHERE username = ?", [newLimit, user]);
+    //Returns false for unknown kinds
+    async setNotification(userId, kind, enabled) {
+        const column = notificationColumns[kind];
+        if(!column) {
+            return false;
+        }
+        const old = (await this.getNotifications(userId))[kind];
+        await this.query("UPDATE counts SET " + column + " = ? WHERE userId = ?", [enabled, userId]);
+        this.settings.delete(userId);
+        await this.audit(userId, column, old ? 'on' : 'off', enabled ? 'on' : 'off');
+        return true;
+    }
+
+    async getAutoReset(userId) {
+        const settings = await this.getSettings(userId);
+        return Boolean(settings['autoReset']);
+    }
+
+    async setAutoReset(userId, enabled) {
+        const old = await this.getAutoReset(userId);
+        await this.query("UPDATE counts SET autoReset = ? WHERE userId = ?", [enabled, userId]);
+        this.settings.delete(userId);
+        return this.audit(userId, 'autoReset', old ? 'on' : 'off', enabled ? 'on' : 'off');
+    }
+
+    //Amounts are stored separated by spaces, null to remove them
+    async getQuickAdd(userId) {
+        const rows = await this.query("SELECT quickAdd FROM counts WHERE userId = ?", [userId]);
+        return rows[0]['quickAdd'];
+    }
+
+    async setQuickAdd(userId, amounts) {
+        const rows = await this.query("SELECT quickAdd FROM counts WHERE userId = ?", [userId]);
+        await this.query("UPDATE counts SET quickAdd = ? WHERE userId = ?", [amounts, userId]);
+        return this.audit(userId, 'quickAdd', rows[0]['quickAdd'], amounts);
+    }
+
+    async getLanguage(userId) {
+        const settings = await this.getSettings(userId);
+        return settings ? settings['language'] : null;
+    }
+
+    //Users who never chose a language keep the one of their client, needed for messages not answering them
+    async setDefaultLanguage(userId, language) {
+        const settings = await this.getSettings(userId);
+        if(!settings || settings['language'] || !language) {
+            return false;
+        }
+        await this.query("UPDATE counts SET language = ? WHERE userId = ? AND language IS NULL", [language, userId]);
+        this.settings.delete(userId);
+        return true;
     }
 
-    async addAmount(user, amount) {
-        const current = await this.getAmount(user);
-        if(current + amount > await this.getLimit(user)) {
+    async setLanguage(userId, language) {
+        const old = await this.getLanguage(userId);
+        await this.query("UPDATE counts SET language = ? WHERE userId = ?", [language, userId]);
+        this.settings.delete(userId);
+        return this.audit(userId, 'language', old, language);
+    }
+
+    async getLiters(userId) {
+        const rows = await this.query("SELECT liters FROM counts WHERE userId = ?", [userId]);
+        return rows[0]['liters'];
+    }
+
+    //Part of the amount spent on expenses with liters, to work out the average price
+    async getPaidWithLiters(userId) {
+        const rows = await this.query("SELECT paidWithLiters FROM counts WHERE userId = ?", [userId]);
+        return rows[0]['paidWithLiters'];
+    }
+
+    //A limit for this month only keeps the previous one in baseLimit to restore it next month
+    async setLimit(userId, newLimit, thisMonthOnly = false, source = 'user') {
+        const old = await this.getLimit(userId);
+        if(thisMonthOnly) {
+            await this.query("UPDATE counts SET baseLimit = COALESCE(baseLimit, payLimit), payLimit = ? WHERE userId = ?", [newLimit, userId]);
+        } else {
+            await this.query("UPDATE counts SET payLimit = ?, baseLimit = NULL WHERE userId = ?", [newLimit, userId]);
+        }
+        this.settings.delete(userId);
+        return this.audit(userId, 'payLimit', old, newLimit, source);
+    }
+
+    async restoreLimits() {
+        const rows = await this.query("SELECT userId, baseLimit FROM counts WHERE baseLimit IS NOT NULL");
+        for(const row of rows) {
+            await this.setLimit(row['userId'], row['baseLimit'], false, 'scheduler');
+        }
+    }
+
+    //Force adds the amount even if it exceeds the limit
+    async addAmount(userId, amount, liters = 0, force = false) {
+        //Checked and updated in a single statement so concurrent expenses cannot exceed the limit together
+        const withLiters = liters > 0 ? amount : 0;
+        const result = await this.query("UPDATE counts SET paid = paid + ?, liters = liters + ?, paidWithLiters = paidWithLiters + ?, lastExpense = NOW() " +
+            "WHERE userId = ? AND (? OR paid + ? <= payLimit)",
+            [amount, liters, withLiters, userId, force, amount]);
+        if(result.affectedRows == 0) {
+            //Either over the limit or not registered, the latter fails reading the amount
+            await this.getAmount(userId);
             return -1;
         }
-        await this.conn.query("UPDATE counts SET paid = ? WHERE username = ?", [current + amount, user]);
+        const current = await this.getAmount(userId);
+        await this.query("INSERT INTO journal(userId, paid, liters, paidWithLiters) SELECT userId, paid - ?, liters - ?, paidWithLiters - ? FROM counts WHERE userId = ?",
+            [amount, liters, withLiters, userId]);
+        await this.trimJournal(userId);
+        //Expenses over the limit are told apart in the history
+        await this.audit(userId, force ? 'paidForced' : 'paid', current - amount, current);
+        return current;
+    }
+
+    //Recurring expenses are always added and are not activity of the user for the reminders
+    async addRecurringAmount(userId, amount) {
+        const current = await this.getAmount(userId);
+        await this.journal(userId);
+        await this.query("UPDATE counts SET paid = paid + ? WHERE userId = ?", [amount, userId]);
+        await this.audit(userId, 'paidRecurring', current, current + amount, 'scheduler');
         return current + amount;
     }
 
-    reset(user) {
-        return this.conn.query("UPDATE counts SET paid = ? WHERE username = ?", [0, user]);
+    //Field tells apart in the audit undone expenses from refunds
+    async removeAmount(userId, amount, liters, field = 'paid') {
+        const current = await this.getAmount(userId);
+        await this.journal(userId);
+        await this.query("UPDATE counts SET paid = GREATEST(paid - ?, 0), liters = GREATEST(liters - ?, 0), paidWithLiters = GREATEST(paidWithLiters - ?, 0) WHERE userId = ?",
+            [amount, liters, liters > 0 ? amount : 0, userId]);
+        return this.audit(userId, field, current, Math.max(current - amount, 0));
+    }
+
+    refund(userId, amount) {
+        return this.removeAmount(userId, amount, 0, 'refund');
+    }
+
+    //A new month cannot be undone back to the previous one so its journal is cleared instead
+    async reset(userId, newMonth = false) {
+        const current = await this.getAmount(userId);
+        if(newMonth) {
+            await this.query("DELETE FROM journal WHERE userId = ?", [userId]);
+        } else {
+            await this.journal(userId);
+        }
+        await this.query("UPDATE counts SET paid = ?, liters = ?, paidWithLiters = ? WHERE userId = ?", [0, 0, 0, userId]);
+        return this.audit(userId, 'paid', current, 0, newMonth ? 'scheduler' : 'user');
+    }
+
+    //Stores the current amounts so the next change can be undone
+    async journal(userId) {
+        await this.query("INSERT INTO journal(userId, paid, liters, paidWithLiters) SELECT userId, paid, liters, paidWithLiters FROM counts WHERE userId = ?", [userId]);
+        return this.trimJournal(userId);
+    }
+
+    trimJournal(userId) {
+        return this.query("DELETE FROM journal WHERE userId = ? AND id <= " +
+            "(SELECT id FROM (SELECT id FROM journal WHERE userId = ? ORDER BY id DESC LIMIT 1 OFFSET ?) AS oldest)",
+            [userId, userId, journalSize]);
+    }
+
+    async undo(userId) {
+        const rows = await this.query("SELECT id, paid, liters, paidWithLiters FROM journal WHERE userId = ? ORDER BY id DESC LIMIT 1", [userId]);
+        if(rows.length == 0) {
+            return false;
+        }
+        const current = await this.getAmount(userId);
+        await this.query("UPDATE counts SET paid = ?, liters = ?, paidWithLiters = ? WHERE userId = ?",
+            [rows[0]['paid'], rows[0]['liters'], rows[0]['paidWithLiters'], userId]);
+        await this.query("DELETE FROM journal WHERE id = ?", [rows[0]['id']]);
+        await this.audit(userId, 'paid', current, rows[0]['paid']);
+        return true;
+    }
+
+    //Records the change of a field of the user to be shown in /history
+    //Source is user for changes asked by the user and scheduler for the automatic ones
+    audit(userId, field, oldValue, newValue, source = 'user') {
+        return this.query("INSERT INTO audit(userId, field, oldValue, newValue, source) VALUES (?, ?, ?, ?, ?)",
+            [userId, field, oldValue, newValue, source]);
+    }
+
+    //Changes of every field unless one is given
+    getHistory(userId, count, field = null) {
+        return this.query("SELECT field, oldValue, newValue, changedAt, source FROM audit WHERE userId = ? AND (? IS NULL OR field = ?) ORDER BY id DESC LIMIT ?",
+            [userId, field, field, count]);
+    }
+
+    //Rows of the user in every table, null if not registered
+    async getUserData(userId) {
+        const userData = {};
+        for(const table of userTables) {
+            userData[table] = await this.query("SELECT * FROM " + table + " WHERE userId = ?", [userId]);
+        }
+        return userData['counts'].length ? userData : null;
+    }
+
+    //Cadence is monthly, with the day of the month, or weekly, with the day of the week starting on Monday as 1
+    async addRecurring(userId, amount, cadence, day) {
+        await this.query("INSERT INTO recurring(userId, amount, cadence, day) VALUES (?, ?, ?, ?)", [userId, amount, cadence, day]);
+        return this.audit(userId, 'recurring', null, [amount, cadence, day].join(' '));
+    }
+
+    getRecurring(userId) {
+        return this.query("SELECT id, amount, cadence, day FROM recurring WHERE userId = ? ORDER BY id", [userId]);
+    }
+
+    //Returns false if the user has no recurring expense with that id
+    async removeRecurring(userId, id) {
+        const rows = await this.query("SELECT amount, cadence, day FROM recurring WHERE userId = ? AND id = ?", [userId, id]);
+        const result = await this.query("DELETE FROM recurring WHERE userId = ? AND id = ?", [userId, id]);
+        if(result.affectedRows == 0) {
+            return false;
+        }
+        await this.audit(userId, 'recurring', [rows[0]['amount'], rows[0]['cadence'], rows[0]['day']].join(' '), null);
+        return true;
+    }
+
+    getAllRecurring() {
+        return this.query("SELECT recurring.id, recurring.userId, amount, cadence, day, username, chatId, active FROM recurring JOIN counts USING (userId)");
+    }
+
+    async forget(userId) {
+        for(const table of userTables) {
+            await this.query("DELETE FROM " + table + " WHERE userId = ?", [userId]);
+        }
+        this.settings.delete(userId);
+    }
+
+    //Returns false if the message was already processed, e.g. redelivered by Telegram after a restart
+    async markProcessed(chatId, messageId) {
+        const result = await this.query("INSERT IGNORE INTO processed(chatId, messageId) VALUES (?, ?)", [chatId, messageId]);
+        return result.affectedRows > 0;
+    }
+
+    //For messages whose change failed, so they are handled again if redelivered
+    unmarkProcessed(chatId, messageId) {
+        return this.query("DELETE FROM processed WHERE chatId = ? AND messageId = ?", [chatId, messageId]);
+    }
+
+    cleanProcessed(days) {
+        return this.query("DELETE FROM processed WHERE processedAt < NOW() - INTERVAL ? DAY", [days]);
+    }
+
+    //Contents of every table, for backups
+    async dump() {
+        const tables = {};
+        for(const table of ['counts', 'journal', 'audit', 'recurring']) {
+            tables[table] = await this.query("SELECT * FROM " + table);
+        }
+        return tables;
+    }
+
+    getUsersToReset() {
+        return this.query("SELECT userId, chatId, paid, payLimit, notifySummary, active FROM counts WHERE autoReset");
+    }
+
+    //Users wanting reminders whose last expense was exactly the given days ago
+    getUsersToRemind(days) {
+        return this.query("SELECT userId, chatId FROM counts WHERE active AND notifyReminders AND DATEDIFF(CURDATE(), lastExpense) = ?", [days]);
+    }
+
+    //Every user with the date of its last change, most recently active first
+    getUsersOverview() {
+        return this.query("SELECT userId, username, chatId, payLimit, paid, " +
+            "(SELECT MAX(changedAt) FROM audit WHERE audit.userId = counts.userId) AS lastActivity " +
+            "FROM counts ORDER BY lastActivity DESC");
+    }
+
+    getAllUsers() {
+        return this.query("SELECT userId, chatId, notifySummary, notifyAnnouncements FROM counts WHERE active");
+    }
+
+    //Null if never set
+    async getState(name) {
+        const rows = await this.query("SELECT value FROM state WHERE name = ?", [name]);
+        return rows.length ? rows[0]['value'] : null;
+    }
+
+    setState(name, value) {
+        return this.query("INSERT INTO state(name, value) VALUES (?, ?) ON DUPLICATE KEY UPDATE value = ?", [name, value, value]);
     }
 
     close() {
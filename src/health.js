@@ -0,0 +1,42 @@
+const http = require('http');
+require('log-timestamp');
+
+class Health {
+    //Checks is a map of name to a function returning a promise that rejects when unhealthy
+    //Metrics is a function returning the counters to serve in /metrics
+    constructor(port, checks, metrics) {
+        this.checks = checks;
+        this.metrics = metrics;
+        this.server = http.createServer((req, res) => this.handle(req, res));
+        this.server.listen(port, () => console.log("Health check listening on port", port));
+    }
+
+    handle(req, res) {
+        if(req.url == '/metrics') {
+            res.writeHead(200, {'Content-Type': 'application/json'});
+            res.end(JSON.stringify(this.metrics()));
+            return;
+        }
+        if(req.url != '/healthz') {
+            res.writeHead(404);
+            res.end();
+            return;
+        }
+        const names = Object.keys(this.checks);
+        Promise.allSettled(names.map(name => this.checks[name]()))
+            .then(results => {
+                const report = {};
+                results.forEach((result, i) => report[names[i]] = result.status == 'fulfilled' ? "ok" : String(result.reason));
+                const healthy = results.every(result => result.status == 'fulfilled');
+                res.writeHead(healthy ? 200 : 503, {'Content-Type': 'application/json'});
+                res.end(JSON.stringify(report));
+            });
+    }
+
+    close() {
+        console.log("Health check is closing...");
+        this.server.close();
+    }
+}
+
+module.exports.Health = Health;
@@ -0,0 +1,293 @@
+//Descriptions shown in the Telegram command menu
+const commands = {
+    en: {
+        start: "Register and start counting your fuel expenses",
+        check: "Show the amount spent and what is left",
+        refund: "Deduct a refunded amount from the amount spent",
+        recurring: "Manage expenses recorded automatically",
+        reset: "Set the amount spent back to 0",
+        undo: "Revert the last change to the amount spent",
+        history: "Show the last changes",
+        limit_history: "Show the last changes of the limit",
+        prices: "Cheapest stations in a Spanish postcode",
+        export_all: "Download all your data as JSON",
+        forget: "Delete all your data",
+        help: "Show the help",
+        announce: "Send a message to every user",
+        backup: "Get a backup of the database",
+        users: "List the registered users"
+    },
+    es: {
+        start: "Regístrate y empieza a contar tus gastos de combustible",
+        check: "Muestra lo gastado y lo que queda",
+        refund: "Descuenta un importe reembolsado de lo gastado",
+        recurring: "Gestiona gastos registrados automáticamente",
+        reset: "Pone a 0 el importe gastado",
+        undo: "Revierte el último cambio del importe gastado",
+        history: "Muestra los últimos cambios",
+        limit_history: "Muestra los últimos cambios del límite",
+        prices: "Gasolineras más baratas de un código postal",
+        export_all: "Descarga todos tus datos en JSON",
+        forget: "Borra todos tus datos",
+        help: "Muestra la ayuda",
+        announce: "Envía un mensaje a todos los usuarios",
+        backup: "Obtén una copia de seguridad de la base de datos",
+        users: "Lista los usuarios registrados"
+    }
+};
+
+const catalogs = {
+    en: {
+        spent: "Spent: <b>{0}</b>",
+        left: "Left: <b>{0}</b>",
+        liters: "Liters: <b>{0}</b>",
+        averagePrice: "Average price: <b>{0}</b>/L",
+        pace: "Day {0}/{1}: {2}% of the month, {3}% of the limit used",
+        monthlySummary: "<b>End of month summary</b>",
+        limitExceeded: "<b>Expense exceeds limit!</b>",
+        limitThisMonth: "Limit set to <b>{0}</b> until the end of the month",
+        addAnyway: "Add anyway",
+        inlineTitle: "Left this month: {0}",
+        inlineDescription: "Spent {0} of {1}",
+        inlineSummary: "⛽ Spent <b>{0}</b> of <b>{1}</b>, <b>{2}</b> left",
+        inlineNotStarted: "Start the bot to check your balance",
+        hint: "Send an amount like 45.50 or use /help",
+        somethingWrong: "Something went wrong, please try again later",
+        invalidAmount: "Invalid amount: send a number greater than 0 and up to {0} with at most 2 decimals, e.g. 45 or 45.50",
+        invalidLiters: "Invalid liters: send a number greater than 0 and up to {0}, e.g. 45 32.5L",
+        warnThreshold: "<b>Warning</b>: you have used <b>{0}%</b> of your limit",
+        warnConfigured: "You will be warned at {0}% of your limit",
+        warnUsage: "Use /config warn &lt;percent&gt; with a percent from 0 to 100, 0 to never be warned",
+        languageConfigured: "Language set to English",
+        unknownLanguage: "Unknown language, available ones are: {0}",
+        resetQuestion: "Reset the amount spent?",
+        resetConfirm: "Yes, reset {0}",
+        resetDone: "Reset done",
+        forgetQuestion: "Delete all your data? This cannot be undone",
+        forgetConfirm: "Yes, delete my data",
+        forgetDone: "Your data has been deleted. Use /start to register again",
+        cancel: "Cancel",
+        cancelled: "Cancelled",
+        undo: "Undo",
+        undone: "Expense of {0} undone",
+        notYourButton: "This button is for someone else",
+        staleButton: "This button is from a previous month",
+        refunded: "Refund of <b>{0}</b> recorded",
+        refundField: "Refund",
+        recurringAdded: "Recurring expense added",
+        recurringRemoved: "Recurring expense #{0} removed",
+        recurringNotFound: "There is no recurring expense #{0}",
+        recurringEmpty: "No recurring expenses",
+        recurringMonthly: "<b>{0}</b> every month on day {1}",
+        recurringWeekly: "<b>{0}</b> every week on day {1}",
+        recurringRecorded: "Recurring expense of <b>{0}</b> recorded",
+        recurringUsage: "Use /recurring add &lt;amount&gt; monthly &lt;1-31&gt;, /recurring add &lt;amount&gt; weekly &lt;1-7&gt;, " +
+            "/recurring list or /recurring remove &lt;number&gt;",
+        check: "Check",
+        undoDone: "Last change undone",
+        nothingToUndo: "Nothing to undo",
+        adminOnly: "Only admins can use this command",
+        notAllowed: "Sorry, this bot is private",
+        notRegistered: "You are not registered, use /start first",
+        usersTitle: "<b>{0} users</b>",
+        userEntry: "{0} (chat {1}): {2} of {3}, last active {4}",
+        announceReport: "Announcement delivered to {0} of {1} chats",
+        noHistory: "No changes recorded",
+        historyEntry: "<code>{0}</code> {1}: {2} → {3} <i>({4})</i>",
+        userSource: "by you",
+        schedulerSource: "automatic",
+        userField: "User",
+        paidField: "Spent",
+        paidForcedField: "Spent over the limit",
+        paidRecurringField: "Recurring expense",
+        recurringField: "Recurring expenses",
+        payLimitField: "Limit",
+        warnPercentField: "Warning %",
+        languageField: "Language",
+        notifySummaryField: "Summary notifications",
+        notifyAnnouncementsField: "Announcements",
+        notifyWarningsField: "Warning notifications",
+        notifyRemindersField: "Reminders",
+        autoResetOn: "The amount spent will be reset at the start of every month",
+        autoResetOff: "The amount spent will only be reset with /reset",
+        newMonth: "New month started, your limit is <b>{0}</b>. Last month you spent <b>{1}</b>",
+        autoResetField: "Auto reset",
+        unknownFuel: "Unknown fuel, available ones are: {0}",
+        noStations: "No stations found for postcode {0}",
+        averageAbove: "Your average price is <b>{0}</b>/L, {1} more than the cheapest",
+        averageBelow: "Your average price is <b>{0}</b>/L, not more than the cheapest",
+        quickAddConfigured: "Tap an amount below to record it",
+        quickAddRemoved: "Quick add amounts removed",
+        quickAddUsage: "Use /config quickadd followed by up to 8 amounts, e.g. /config quickadd 50 60 70",
+        quickAddField: "Quick add",
+        reminder: "You haven't logged a fill-up in {0} days, did you forget one?",
+        on: "on",
+        off: "off",
+        notificationsStatus: "Notifications\nEnd of month summary: {0}\nAnnouncements: {1}\nLimit warnings: {2}\nReminders: {3}",
+        notificationsUsage: "Use /config notifications &lt;summary|announcements|warnings|reminders&gt; &lt;on|off&gt;",
+        help:
+            "/start - Register and start counting your fuel expenses\n" +
+            "/check - Show the amount spent and what is left\n" +
+            "/refund &lt;amount&gt; - Deduct a refunded amount, e.g. /refund 10.00\n" +
+            "/recurring [add|list|remove] - Expenses recorded automatically every month or week (Monday is 1), e.g. /recurring add 30 monthly 1\n" +
+            "/reset - Set the amount spent back to 0, done every month unless /config autoreset off\n" +
+            "/undo - Revert the last change to the amount spent\n" +
+            "/history [n] - Show the last n changes, 10 by default\n" +
+            "/limit_history [n] - Show the last n changes of the limit, 10 by default\n" +
+            "/config limit &lt;amount&gt; [--this-month-only] - Change your limit, e.g. /config limit 200\n" +
+            "/config warn &lt;percent&gt; - Warn when spending reaches a percentage of the limit, 0 to disable, e.g. /config warn 80\n" +
+            "/config language &lt;en|es&gt; - Change the language of the bot, e.g. /config language es\n" +
+            "/config autoreset &lt;on|off&gt; - Reset the amount spent at the start of every month\n" +
+            "/config quickadd [amounts] - Buttons to record usual amounts with a tap, none to remove them, e.g. /config quickadd 50 60 70\n" +
+            "/config notifications [summary|announcements|warnings|reminders] [on|off] - Show or switch notifications, e.g. /config notifications summary off\n" +
+            "/prices &lt;postcode&gt; [95|98|diesel] - Cheapest stations in a Spanish postcode, e.g. /prices 28001 diesel\n" +
+            "/export_all - Download all your data as JSON\n" +
+            "/forget - Delete all your data\n" +
+            "/help - Show this help\n" +
+            "\n" +
+            "Send just the amount of a fill-up to record it, e.g. 45, 45.50 or 1,045.50\n" +
+            "Add the liters to track the price per liter, e.g. 45.50 32.1L"
+    },
+    es: {
+        spent: "Gastado: <b>{0}</b>",
+        left: "Restante: <b>{0}</b>",
+        liters: "Litros: <b>{0}</b>",
+        averagePrice: "Precio medio: <b>{0}</b>/L",
+        pace: "Día {0}/{1}: {2}% del mes, {3}% del límite usado",
+        monthlySummary: "<b>Resumen de fin de mes</b>",
+        limitExceeded: "<b>¡El gasto supera el límite!</b>",
+        limitThisMonth: "Límite fijado en <b>{0}</b> hasta final de mes",
+        addAnyway: "Añadir de todos modos",
+        inlineTitle: "Restante este mes: {0}",
+        inlineDescription: "Gastado {0} de {1}",
+        inlineSummary: "⛽ Gastado <b>{0}</b> de <b>{1}</b>, quedan <b>{2}</b>",
+        inlineNotStarted: "Inicia el bot para consultar tu saldo",
+        hint: "Envía un importe como 45.50 o usa /help",
+        somethingWrong: "Algo ha ido mal, inténtalo de nuevo más tarde",
+        invalidAmount: "Importe no válido: envía un número mayor que 0 y hasta {0} con 2 decimales como mucho, p. ej. 45 o 45.50",
+        invalidLiters: "Litros no válidos: envía un número mayor que 0 y hasta {0}, p. ej. 45 32,5L",
+        warnThreshold: "<b>Aviso</b>: has usado el <b>{0}%</b> de tu límite",
+        warnConfigured: "Se te avisará al llegar al {0}% de tu límite",
+        warnUsage: "Usa /config warn &lt;porcentaje&gt; con un porcentaje de 0 a 100, 0 para no recibir avisos",
+        languageConfigured: "Idioma cambiado a español",
+        unknownLanguage: "Idioma desconocido, los disponibles son: {0}",
+        resetQuestion: "¿Reiniciar el importe gastado?",
+        resetConfirm: "Sí, reiniciar {0}",
+        resetDone: "Reiniciado",
+        forgetQuestion: "¿Borrar todos tus datos? No se puede deshacer",
+        forgetConfirm: "Sí, borrar mis datos",
+        forgetDone: "Tus datos han sido borrados. Usa /start para registrarte de nuevo",
+        cancel: "Cancelar",
+        cancelled: "Cancelado",
+        undo: "Deshacer",
+        undone: "Gasto de {0} deshecho",
+        notYourButton: "Este botón es para otra persona",
+        staleButton: "Este botón es de un mes anterior",
+        refunded: "Reembolso de <b>{0}</b> registrado",
+        refundField: "Reembolso",
+        recurringAdded: "Gasto periódico añadido",
+        recurringRemoved: "Gasto periódico #{0} eliminado",
+        recurringNotFound: "No existe el gasto periódico #{0}",
+        recurringEmpty: "No hay gastos periódicos",
+        recurringMonthly: "<b>{0}</b> cada mes el día {1}",
+        recurringWeekly: "<b>{0}</b> cada semana el día {1}",
+        recurringRecorded: "Gasto periódico de <b>{0}</b> registrado",
+        recurringUsage: "Usa /recurring add &lt;importe&gt; monthly &lt;1-31&gt;, /recurring add &lt;importe&gt; weekly &lt;1-7&gt;, " +
+            "/recurring list o /recurring remove &lt;número&gt;",
+        check: "Consultar",
+        undoDone: "Último cambio deshecho",
+        nothingToUndo: "No hay nada que deshacer",
+        adminOnly: "Solo los administradores pueden usar este comando",
+        notAllowed: "Lo siento, este bot es privado",
+        notRegistered: "No estás registrado, usa /start primero",
+        usersTitle: "<b>{0} usuarios</b>",
+        userEntry: "{0} (chat {1}): {2} de {3}, última actividad {4}",
+        announceReport: "Anuncio entregado en {0} de {1} chats",
+        noHistory: "No hay cambios registrados",
+        historyEntry: "<code>{0}</code> {1}: {2} → {3} <i>({4})</i>",
+        userSource: "por ti",
+        schedulerSource: "automático",
+        userField: "Usuario",
+        paidField: "Gastado",
+        paidForcedField: "Gastado sobre el límite",
+        paidRecurringField: "Gasto recurrente",
+        recurringField: "Gastos recurrentes",
+        payLimitField: "Límite",
+        warnPercentField: "Aviso %",
+        languageField: "Idioma",
+        notifySummaryField: "Notificaciones de resumen",
+        notifyAnnouncementsField: "Anuncios",
+        notifyWarningsField: "Notificaciones de aviso",
+        notifyRemindersField: "Recordatorios",
+        autoResetOn: "El importe gastado se reiniciará al comienzo de cada mes",
+        autoResetOff: "El importe gastado solo se reiniciará con /reset",
+        newMonth: "Nuevo mes, tu límite es <b>{0}</b>. El mes pasado gastaste <b>{1}</b>",
+        autoResetField: "Reinicio automático",
+        unknownFuel: "Combustible desconocido, los disponibles son: {0}",
+        noStations: "No se han encontrado gasolineras en el código postal {0}",
+        averageAbove: "Tu precio medio es <b>{0}</b>/L, {1} más que la más barata",
+        averageBelow: "Tu precio medio es <b>{0}</b>/L, no más que la más barata",
+        quickAddConfigured: "Pulsa un importe para registrarlo",
+        quickAddRemoved: "Importes rápidos eliminados",
+        quickAddUsage: "Usa /config quickadd seguido de hasta 8 importes, p. ej. /config quickadd 50 60 70",
+        quickAddField: "Importes rápidos",
+        reminder: "No has registrado ningún repostaje en {0} días, ¿se te ha olvidado alguno?",
+        on: "activado",
+        off: "desactivado",
+        notificationsStatus: "Notificaciones\nResumen de fin de mes: {0}\nAnuncios: {1}\nAvisos de límite: {2}\nRecordatorios: {3}",
+        notificationsUsage: "Usa /config notifications &lt;summary|announcements|warnings|reminders&gt; &lt;on|off&gt;",
+        help:
+            "/start - Regístrate y empieza a contar tus gastos de combustible\n" +
+            "/check - Muestra lo gastado y lo que queda\n" +
+            "/refund &lt;importe&gt; - Descuenta un importe reembolsado, p. ej. /refund 10.00\n" +
+            "/recurring [add|list|remove] - Gastos registrados automáticamente cada mes o semana (el lunes es 1), p. ej. /recurring add 30 monthly 1\n" +
+            "/reset - Pone a 0 el importe gastado, se hace cada mes salvo con /config autoreset off\n" +
+            "/undo - Revierte el último cambio del importe gastado\n" +
+            "/history [n] - Muestra los últimos n cambios, 10 por defecto\n" +
+            "/limit_history [n] - Muestra los últimos n cambios del límite, 10 por defecto\n" +
+            "/config limit &lt;importe&gt; [--this-month-only] - Cambia tu límite, p. ej. /config limit 200\n" +
+            "/config warn &lt;porcentaje&gt; - Avisa al alcanzar un porcentaje del límite, 0 para desactivar, p. ej. /config warn 80\n" +
+            "/config language &lt;en|es&gt; - Cambia el idioma del bot, p. ej. /config language en\n" +
+            "/config autoreset &lt;on|off&gt; - Reinicia el importe gastado al comienzo de cada mes\n" +
+            "/config quickadd [importes] - Botones para registrar importes habituales, ninguno para quitarlos, p. ej. /config quickadd 50 60 70\n" +
+            "/config notifications [summary|announcements|warnings|reminders] [on|off] - Muestra o cambia las notificaciones, p. ej. /config notifications summary off\n" +
+            "/prices &lt;código postal&gt; [95|98|diesel] - Gasolineras más baratas del código postal, p. ej. /prices 28001 diesel\n" +
+            "/export_all - Descarga todos tus datos en JSON\n" +
+            "/forget - Borra todos tus datos\n" +
+            "/help - Muestra esta ayuda\n" +
+            "\n" +
+            "Envía solo el importe de un repostaje para registrarlo, p. ej. 45, 45,50 o 1.045,50\n" +
+            "Añade los litros para controlar el precio por litro, p. ej. 45.50 32.1L"
+    }
+};
+
+const defaultLanguage = 'en';
+
+//Accepts IETF tags as sent by Telegram, e.g. es-ES
+function supported(language) {
+    const code = (language || '').split('-')[0];
+    return catalogs[code] ? code : null;
+}
+
+function escape(value) {
+    return String(value).replace(/&/g, '&amp;').replace(/</g, '&lt;').replace(/>/g, '&gt;');
+}
+
+//Messages are formatted as Telegram HTML, the arguments are escaped
+function text(language, key, ...args) {
+    const catalog = catalogs[supported(language) || defaultLanguage];
+    const message = catalog[key] !== undefined ? catalog[key] : catalogs[defaultLanguage][key];
+    return message.replace(/\{(\d+)\}/g, (match, index) => escape(args[index]));
+}
+
+//Commands with their description in the language, as expected by setMyCommands
+function commandList(language, names) {
+    return names.map(command => ({command, description: commands[language][command]}));
+}
+
+module.exports.languages = Object.keys(catalogs);
+module.exports.keys = Object.keys(catalogs[defaultLanguage]);
+module.exports.commandList = commandList;
+module.exports.supported = supported;
+module.exports.escape = escape;
+module.exports.text = text;
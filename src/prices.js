@@ -0,0 +1,75 @@
+const config = require('./settings.js');
+const https = require('https');
+require('log-timestamp');
+
+//Prices of every station in Spain published by the Ministry, updated every half an hour
+const defaultUrl = "https://sedeaplicaciones.minetur.gob.es/ServiciosRESTCarburantes/PreciosCarburantes/EstacionesTerrestres/";
+const cacheTime = 30 * 60 * 1000;
+//Time without receiving data before the download is abandoned
+const downloadTimeout = 30 * 1000;
+
+const fuels = {
+    '95': 'Precio Gasolina 95 E5',
+    '98': 'Precio Gasolina 98 E5',
+    'diesel': 'Precio Gasoleo A'
+};
+
+class Prices {
+    constructor() {
+        this.stations = null;
+        this.fetched = 0;
+    }
+
+    download() {
+        return new Promise((resolve, reject) => {
+            const req = https.get(config.app.pricesUrl || defaultUrl, res => {
+                if(res.statusCode != 200) {
+                    res.resume();
+                    reject(new Error("Prices service answered " + res.statusCode));
+                    return;
+                }
+                var body = '';
+                res.setEncoding('utf8');
+                res.on('data', chunk => body += chunk);
+                res.on('end', () => {
+                    try {
+                        resolve(JSON.parse(body)['ListaEESSPrecio']);
+                    } catch(err) {
+                        reject(err);
+                    }
+                });
+            }).on('error', reject);
+            req.setTimeout(downloadTimeout, () => req.destroy(new Error("Prices service did not answer in " + downloadTimeout / 1000 + "s")));
+        });
+    }
+
+    async getStations() {
+        if(!this.stations || Date.now() - this.fetched > cacheTime) {
+            this.stations = await this.download();
+            this.fetched = Date.now();
+        }
+        return this.stations;
+    }
+
+    //Cheapest stations in the postcode for the fuel (95, 98 or diesel), null if the fuel is unknown
+    async cheapest(postcode, fuel, count) {
+        const field = fuels[fuel];
+        if(!field) {
+            return null;
+        }
+        const stations = await this.getStations();
+        return stations
+            .filter(station => station['C.P.'] == postcode && station[field])
+            .map(station => ({
+                name: station['Rótulo'],
+                address: station['Dirección'] + ", " + station['Localidad'],
+                //Prices come with decimal comma
+                price: parseFloat(station[field].replace(',', '.'))
+            }))
+            .sort((a, b) => a.price - b.price)
+            .slice(0, count);
+    }
+}
+
+module.exports.fuels = Object.keys(fuels);
+module.exports.Prices = Prices;
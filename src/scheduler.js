@@ -0,0 +1,33 @@
+require('log-timestamp');
+
+class Scheduler {
+    constructor() {
+        this.timers = new Map();
+    }
+
+    //Runs the job every day at the given hour (server time)
+    daily(name, hour, job) {
+        if(!Number.isInteger(hour) || hour < 0 || hour > 23) {
+            throw new Error("Invalid hour for scheduled job " + name + ": " + hour);
+        }
+        const now = new Date();
+        const next = new Date(now.getFullYear(), now.getMonth(), now.getDate(), hour);
+        if(next <= now) {
+            next.setDate(next.getDate() + 1);
+        }
+        var that = this;
+        this.timers.set(name, setTimeout(function() {
+            console.log("Running scheduled job:", name);
+            job();
+            that.daily(name, hour, job);
+        }, next - now));
+    }
+
+    stop() {
+        console.log("Scheduler is stopping...");
+        this.timers.forEach(timer => clearTimeout(timer));
+        this.timers.clear();
+    }
+}
+
+module.exports.Scheduler = Scheduler;
@@ -0,0 +1,42 @@
+const fs = require('fs');
+const os = require('os');
+const path = require('path');
+
+//The first config file found among --config, CONFIG_PATH, src/config.js and the standard locations is loaded
+function candidates() {
+    const paths = [];
+    const flag = process.argv.indexOf('--config');
+    if (flag != -1 && process.argv[flag + 1]) {
+        paths.push(process.argv[flag + 1]);
+    }
+    if (process.env.CONFIG_PATH) {
+        paths.push(process.env.CONFIG_PATH);
+    }
+    paths.push(path.join(__dirname, 'config.js'));
+    paths.push(path.join(process.env.XDG_CONFIG_HOME || path.join(os.homedir(), '.config'), 'fuel-bot', 'config.js'));
+    paths.push('/etc/fuel-bot/config.js');
+    return paths.map(file => path.resolve(file));
+}
+
+function load() {
+    const paths = candidates();
+    const found = paths.find(file => fs.existsSync(file));
+    if (!found) {
+        throw new Error("No config file found, looked in " + paths.join(", "));
+    }
+    const config = require(found);
+    //Sections of config.<APP_ENV>.js next to the config file override the matching ones
+    if (process.env.APP_ENV) {
+        const overlay = found.replace(/\.js$/, '.' + process.env.APP_ENV + '.js');
+        if (!fs.existsSync(overlay)) {
+            throw new Error("No config file for environment " + process.env.APP_ENV + ", expected " + overlay);
+        }
+        const profile = require(overlay);
+        for (const section of Object.keys(profile)) {
+            config[section] = Object.assign({}, config[section], profile[section]);
+        }
+    }
+    return config;
+}
+
+module.exports = load();
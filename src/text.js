@@ -0,0 +1,66 @@
+//Parsing of the amounts users send and splitting of the texts the bot answers, free of side effects to be testable
+
+//Separators follow the language, 1,234.56 in English and 1.234,56 in Spanish, and a plain decimal point is always accepted
+//Returns NaN for anything else
+function parseAmount(text, language) {
+    const [thousands, decimal] = (language || '').split('-')[0] == 'es' ? ['.', ','] : [',', '.'];
+    const grouped = new RegExp("^([1-9]\\d{0,2}(\\" + thousands + "\\d{3})+|\\d+)(\\" + decimal + "\\d+)?$");
+    if(grouped.test(text)) {
+        return parseFloat(text.split(thousands).join('').replace(decimal, '.'));
+    }
+    return /^\d+(\.\d+)?$/.test(text) ? parseFloat(text) : NaN;
+}
+
+//Liters never need thousands so either separator is taken as the decimal one, 32.123 is never 32123 liters
+function parseLiters(text) {
+    return /^\d+([.,]\d+)?$/.test(text) ? parseFloat(text.replace(',', '.')) : NaN;
+}
+
+//Amounts with more than 2 decimals or over the maximum are most likely typos
+function validAmount(amount, max) {
+    return amount > 0 && amount <= max && /^\d+(\.\d{1,2})?$/.test(String(amount));
+}
+
+//Liters over the maximum are bigger than any tank
+function validLiters(liters, max) {
+    return liters > 0 && liters <= max;
+}
+
+//Splits at line boundaries, and lines longer than the limit on their own are cut outside of HTML tags
+//Parts left blank are dropped as Telegram refuses to send them
+function split(text, length) {
+    const parts = [];
+    //Null until the part has a line, as empty lines count too
+    let current = null;
+    for(let line of text.split('\n')) {
+        while(line.length > length) {
+            if(current !== null) {
+                parts.push(current);
+                current = null;
+            }
+            const cut = tagSafeCut(line, length);
+            parts.push(line.slice(0, cut));
+            line = line.slice(cut);
+        }
+        if(current !== null && current.length + 1 + line.length > length) {
+            parts.push(current);
+            current = line;
+        } else {
+            current = current !== null ? current + '\n' + line : line;
+        }
+    }
+    parts.push(current);
+    return parts.filter(part => part.trim().length > 0);
+}
+
+//The cut moves back before a tag it would fall in, unless the tag starts the line
+function tagSafeCut(line, length) {
+    const open = line.lastIndexOf('<', length - 1);
+    return open > 0 && line.indexOf('>', open) >= length ? open : length;
+}
+
+module.exports.parseAmount = parseAmount;
+module.exports.parseLiters = parseLiters;
+module.exports.validAmount = validAmount;
+module.exports.validLiters = validLiters;
+module.exports.split = split;
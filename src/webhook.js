@@ -0,0 +1,27 @@
+const config = require('./settings.js');
+const http = require('http');
+const https = require('https');
+require('log-timestamp');
+
+//Posts the event as JSON to the configured webhook, if any, without waiting for it
+function notify(event, payload) {
+    if(!config.app.webhookUrl) {
+        return;
+    }
+    const body = JSON.stringify(Object.assign({event, date: new Date().toISOString()}, payload));
+    const url = new URL(config.app.webhookUrl);
+    const client = url.protocol == 'https:' ? https : http;
+    const req = client.request(url, {
+        method: 'POST',
+        headers: {'Content-Type': 'application/json', 'Content-Length': Buffer.byteLength(body)}
+    }, res => {
+        res.resume();
+        if(res.statusCode >= 300) {
+            console.log("Webhook answered " + res.statusCode + " to " + event);
+        }
+    });
+    req.on('error', err => console.log("Webhook error:", err.message));
+    req.end(body);
+}
+
+module.exports.notify = notify;
@@ -0,0 +1,34 @@
+const test = require('node:test');
+const assert = require('assert');
+const fs = require('fs');
+const path = require('path');
+const Messages = require('../src/messages.js');
+
+//Rendered texts of every key in every language, regenerate with UPDATE_GOLDEN=1 npm test after changing them on purpose
+const goldenFile = path.join(__dirname, 'golden', 'messages.json');
+
+//Arguments needing escaping to check they never break the HTML
+const args = ['<b>1</b>', 'a & b', '12.5', '3', '4'];
+
+function render() {
+    const rendered = {};
+    for(const language of Messages.languages) {
+        rendered[language] = {};
+        for(const key of Messages.keys) {
+            rendered[language][key] = Messages.text(language, key, ...args);
+        }
+    }
+    return rendered;
+}
+
+test('every message renders as in the golden file', () => {
+    const rendered = render();
+    if(process.env.UPDATE_GOLDEN) {
+        fs.writeFileSync(goldenFile, JSON.stringify(rendered, null, 2) + "\n");
+    }
+    assert.deepStrictEqual(rendered, JSON.parse(fs.readFileSync(goldenFile, 'utf8')));
+});
+
+test('arguments are escaped', () => {
+    assert.strictEqual(Messages.text('en', 'spent', '<b>1</b>'), "Spent: <b>&lt;b&gt;1&lt;/b&gt;</b>");
+});
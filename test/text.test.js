@@ -0,0 +1,105 @@
+const test = require('node:test');
+const assert = require('assert');
+const Text = require('../src/text.js');
+
+//Random amounts with up to 2 decimals and up to millions
+function randomAmounts(count) {
+    return Array.from({length: count}, () => Math.round(Math.random() * 10 ** (2 + Math.floor(Math.random() * 7))) / 100);
+}
+
+function format(amount, locale, grouping) {
+    return amount.toLocaleString(locale, {minimumFractionDigits: 0, maximumFractionDigits: 2, useGrouping: grouping});
+}
+
+test('amounts formatted in the language of the user are parsed back', () => {
+    for(const amount of randomAmounts(1000)) {
+        assert.strictEqual(Text.parseAmount(format(amount, 'en-US', true), 'en'), amount);
+        assert.strictEqual(Text.parseAmount(format(amount, 'es-ES', true), 'es'), amount);
+        assert.strictEqual(Text.parseAmount(format(amount, 'es-ES', false), 'es'), amount);
+    }
+});
+
+test('amounts with a decimal point are parsed in every language', () => {
+    for(const amount of randomAmounts(1000)) {
+        assert.strictEqual(Text.parseAmount(String(amount), 'en'), amount);
+        assert.strictEqual(Text.parseAmount(String(amount), 'es'), amount);
+    }
+});
+
+test('thousands separators follow the language', () => {
+    assert.strictEqual(Text.parseAmount('1,234.56', 'en'), 1234.56);
+    assert.strictEqual(Text.parseAmount('1.234,56', 'es'), 1234.56);
+    assert.strictEqual(Text.parseAmount('45,50', 'es'), 45.5);
+    assert.strictEqual(Text.parseAmount('1.234', 'es'), 1234);
+    assert.strictEqual(Text.parseAmount('1.234', 'en'), 1.234);
+    assert.strictEqual(Text.parseAmount('1.234,56', 'es-ES'), 1234.56);
+});
+
+test('malformed amounts are not parsed', () => {
+    for(const text of ['1,5', '1,23,4', '1.2.3', ',5', '5.', 'abc', '']) {
+        assert.ok(Number.isNaN(Text.parseAmount(text, 'en')), text);
+    }
+    assert.ok(Number.isNaN(Text.parseAmount('1,234.56', 'es')));
+});
+
+test('amounts need at most 2 decimals and be up to the maximum', () => {
+    assert.ok(Text.validAmount(45, 10000));
+    assert.ok(Text.validAmount(0.29, 10000));
+    assert.ok(Text.validAmount(10000, 10000));
+    assert.ok(!Text.validAmount(0, 10000));
+    assert.ok(!Text.validAmount(0.001, 10000));
+    assert.ok(!Text.validAmount(10000.01, 10000));
+    assert.ok(!Text.validAmount(NaN, 10000));
+});
+
+test('liters take either separator as the decimal one', () => {
+    assert.strictEqual(Text.parseLiters('32.123'), 32.123);
+    assert.strictEqual(Text.parseLiters('32,123'), 32.123);
+    assert.strictEqual(Text.parseLiters('40'), 40);
+    for(const text of ['1.234,5', '1,234.5', '32.', 'abc', '']) {
+        assert.ok(Number.isNaN(Text.parseLiters(text)), text);
+    }
+});
+
+test('liters are more than 0 and up to the maximum', () => {
+    assert.ok(Text.validLiters(32.123, 150));
+    assert.ok(Text.validLiters(150, 150));
+    assert.ok(!Text.validLiters(0, 150));
+    assert.ok(!Text.validLiters(150.5, 150));
+    assert.ok(!Text.validLiters(NaN, 150));
+});
+
+test('empty lines are kept when splitting', () => {
+    assert.deepStrictEqual(Text.split('\n\nabc', 100), ['\n\nabc']);
+});
+
+test('blank parts are dropped when splitting', () => {
+    assert.deepStrictEqual(Text.split('abcd\n', 4), ['abcd']);
+    assert.deepStrictEqual(Text.split('abcd\n\n  ', 4), ['abcd']);
+    assert.deepStrictEqual(Text.split('', 100), []);
+});
+
+test('tags are not cut when splitting', () => {
+    assert.deepStrictEqual(Text.split('<b>abcdef</b>', 6), ['<b>abc', 'def', '</b>']);
+    for(let i = 0; i < 200; i++) {
+        const words = Array.from({length: Math.floor(Math.random() * 50)}, () => '<b>' + 'x'.repeat(Math.floor(Math.random() * 20)) + '</b>');
+        for(const part of Text.split(words.join(' '), 30)) {
+            assert.ok(!/<[^>]*$/.test(part), part);
+            assert.ok(!/^[^<]*>/.test(part), part);
+        }
+    }
+});
+
+test('split texts are joined back and fit the limit', () => {
+    for(let i = 0; i < 200; i++) {
+        const lines = Array.from({length: Math.floor(Math.random() * 50)}, () => 'x'.repeat(1 + Math.floor(Math.random() * 120)));
+        const text = lines.join('\n');
+        const parts = Text.split(text, 100);
+        assert.ok(parts.every(part => part.length <= 100));
+        if(lines.every(line => line.length <= 100)) {
+            assert.strictEqual(parts.join('\n'), text);
+        } else {
+            assert.strictEqual(parts.join('').replace(/\n/g, ''), text.replace(/\n/g, ''));
+        }
+    }
+});